@@ -0,0 +1,82 @@
+//! Compare the throughput of file bodies sent with sendfile(2) and buffered copies
+//!
+//! Run with `cargo bench --bench sendfile`. Every round serves FILE_SIZE bytes
+//! REQUESTS times over one keep-alive connection on the loopback interface.
+
+use linda::config::Config;
+use linda::handle_connection;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+
+/// Size of the served file in bytes
+const FILE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Requests per round
+const REQUESTS: usize = 16;
+
+/// Rounds per configuration, the fastest one is reported
+const ROUNDS: usize = 5;
+
+fn main() {
+    let root = env::temp_dir().join(format!("linda-bench-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("large.bin"), vec![b'x'; FILE_SIZE]).unwrap();
+
+    for (name, zero_copy) in [("sendfile", true), ("buffered", false)].iter() {
+        let mut config = Config::new();
+        config
+            .root_mut(root.to_str().unwrap().to_string())
+            .zero_copy_mut(*zero_copy)
+            .max_requests_mut(REQUESTS);
+
+        let fastest = (0..ROUNDS).map(|_| round(config.clone())).min().unwrap();
+        let bytes = (FILE_SIZE * REQUESTS) as f64;
+        println!(
+            "{:>8}: {:>8.2?} for {} requests, {:.0} MiB/s",
+            name,
+            fastest,
+            REQUESTS,
+            bytes / fastest.as_secs_f64() / (1024.0 * 1024.0)
+        );
+    }
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+/// Serve REQUESTS requests for the file on one connection and time them
+fn round(config: Config) -> Duration {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &config).unwrap();
+    });
+
+    let mut client = BufReader::new(TcpStream::connect(address).unwrap());
+    let mut body = vec![0; FILE_SIZE];
+    let start = Instant::now();
+
+    for _ in 0..REQUESTS {
+        client
+            .get_mut()
+            .write_all(b"GET /large.bin HTTP/1.1\r\n\r\n")
+            .unwrap();
+
+        let mut content_length = None;
+        let mut line = String::new();
+        while client.read_line(&mut line).unwrap() > 2 {
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                content_length = Some(value.trim().parse::<usize>().unwrap());
+            }
+            line.clear();
+        }
+        assert_eq!(content_length, Some(FILE_SIZE));
+        client.read_exact(&mut body).unwrap();
+    }
+
+    let elapsed = start.elapsed();
+    server.join().unwrap();
+    elapsed
+}
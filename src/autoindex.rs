@@ -0,0 +1,218 @@
+//! Directory listings for directories without an index file
+//!
+//! Entries are sorted with directories first, then by name.
+//! Hidden entries (starting with `.`) and symbolic links leading out
+//! of the document root are left out.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::date::http_date;
+use crate::uri::percent_encode_path;
+
+/// A file or directory shown in a listing
+#[derive(Debug, Clone)]
+pub struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Entry {
+    /// Get the file name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Get the size in bytes, 0 for directories
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Get the modification time, None if the platform does not record it
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Get the name as a relative link, with a trailing slash for directories
+    ///
+    /// The link starts with `./`, so that a name such as `javascript:alert(1)`
+    /// is not taken for a URI scheme.
+    fn href(&self) -> String {
+        let mut href = format!("./{}", percent_encode_path(&self.name));
+        if self.is_dir {
+            href.push('/');
+        }
+        href
+    }
+}
+
+/// Read the visible entries of `directory`, sorted with directories first, then by name
+///
+/// Entries that are hidden, not valid UTF-8, or resolve outside of `root` are skipped.
+///
+/// # Errors
+///
+/// Returns an io::Error if `directory` can not be read
+pub fn read_entries<P: AsRef<Path>, R: AsRef<Path>>(
+    directory: P,
+    root: R,
+) -> io::Result<Vec<Entry>> {
+    let root = fs::canonicalize(root)?;
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = match entry.file_name().into_string() {
+            Ok(name) if !name.starts_with('.') => name,
+            _ => continue,
+        };
+
+        // Follows symbolic links, dangling ones are skipped
+        let metadata = match fs::metadata(entry.path()) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        match fs::canonicalize(entry.path()) {
+            Ok(resolved) if resolved.starts_with(&root) => (),
+            _ => continue,
+        }
+
+        entries.push(Entry {
+            name,
+            is_dir: metadata.is_dir(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            modified: metadata.modified().ok(),
+        });
+    }
+
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(entries)
+}
+
+/// Render a listing of the directory at URI `path` as an HTML page
+///
+/// # Examples
+///
+/// ```
+/// use linda::autoindex::render_html;
+///
+/// let html = render_html("/docs/<b>/", &[]);
+///
+/// assert!(html.contains("<title>Index of /docs/&lt;b&gt;/</title>"));
+/// assert!(html.contains(r#"<a href="../">../</a>"#));
+/// ```
+pub fn render_html(path: &str, entries: &[Entry]) -> String {
+    let title = format!("Index of {}", escape_html(path));
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n\
+         <body>\n<h1>{0}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Size</th><th>Last modified</th></tr>\n",
+        title
+    );
+
+    if path != "/" {
+        html.push_str("<tr><td><a href=\"../\">../</a></td><td>-</td><td>-</td></tr>\n");
+    }
+
+    for entry in entries {
+        let name = if entry.is_dir {
+            format!("{}/", entry.name)
+        } else {
+            entry.name.clone()
+        };
+        let size = if entry.is_dir {
+            String::from("-")
+        } else {
+            entry.size.to_string()
+        };
+        let modified = entry.modified.map_or_else(|| String::from("-"), http_date);
+
+        html.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&entry.href()),
+            escape_html(&name),
+            size,
+            modified
+        ));
+    }
+
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// Render a listing of the directory at URI `path` as a JSON object
+///
+/// `modified` is in seconds since the Unix epoch, null if unknown.
+///
+/// # Examples
+///
+/// ```
+/// use linda::autoindex::render_json;
+///
+/// assert_eq!(render_json("/a \"b\"/", &[]), r#"{"path":"/a \"b\"/","entries":[]}"#);
+/// ```
+pub fn render_json(path: &str, entries: &[Entry]) -> String {
+    let entries: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let modified = entry
+                .modified
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or_else(|| String::from("null"), |since| since.as_secs().to_string());
+
+            format!(
+                "{{\"name\":{},\"href\":{},\"type\":\"{}\",\"size\":{},\"modified\":{}}}",
+                escape_json(&entry.name),
+                escape_json(&entry.href()),
+                if entry.is_dir { "directory" } else { "file" },
+                entry.size,
+                modified
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"path\":{},\"entries\":[{}]}}",
+        escape_json(path),
+        entries.join(",")
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Quote `text` as a JSON string
+fn escape_json(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
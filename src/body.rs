@@ -0,0 +1,271 @@
+//! Response bodies
+//!
+//! A body is either held in memory, a region of a file, or read from any
+//! reader. Bodies that are not in memory are written out in chunks of
+//! CHUNK_SIZE bytes, so serving a file never loads all of it.
+//! On Linux, files are sent to sockets without copying them at all, see send_to.
+
+use std::fs::File;
+use std::io::{self, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::net::TcpStream;
+use std::{fmt, mem};
+
+#[cfg(target_os = "linux")]
+use crate::sendfile;
+
+/// Number of bytes read from a file or reader at a time when writing a body
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The message body of a Response, with a known length
+///
+/// # Examples
+///
+/// ```
+/// use linda::body::Body;
+///
+/// let mut body = Body::from("Hello, world!");
+/// assert_eq!(body.len(), 13);
+///
+/// let mut written = Vec::new();
+/// body.write_to(&mut written).unwrap();
+/// assert_eq!(written, b"Hello, world!");
+/// assert!(body.is_empty());
+/// ```
+pub enum Body {
+    /// Bytes in memory
+    Bytes(Vec<u8>),
+    /// `len` bytes of a file, starting at `offset`
+    File { file: File, offset: u64, len: u64 },
+    /// The first `len` bytes of a reader
+    Reader {
+        reader: Box<dyn Read + Send>,
+        len: u64,
+    },
+    /// Bodies sent one after the other, e.g. the parts of a multipart body
+    Parts(Vec<Body>),
+}
+
+impl Body {
+    /// Create a body holding all of `file`, from its current length
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors reading the metadata of the file
+    pub fn file(file: File) -> io::Result<Body> {
+        let len = file.metadata()?.len();
+        Ok(Body::File {
+            file,
+            offset: 0,
+            len,
+        })
+    }
+
+    /// Create a body of the first `len` bytes of `reader`
+    pub fn reader<R: Read + Send + 'static>(reader: R, len: u64) -> Body {
+        Body::Reader {
+            reader: Box::new(reader),
+            len,
+        }
+    }
+
+    /// Get the length of the body in bytes
+    pub fn len(&self) -> u64 {
+        match self {
+            Body::Bytes(bytes) => bytes.len() as u64,
+            Body::File { len, .. } | Body::Reader { len, .. } => *len,
+            Body::Parts(parts) => parts.iter().map(Body::len).sum(),
+        }
+    }
+
+    /// Whether the body has no bytes at all
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the bytes of a body held in memory, None for other bodies
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Body::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Get `len` bytes of the body starting at `offset`, without reading them
+    ///
+    /// # Errors
+    ///
+    /// * ErrorKind::InvalidInput if the slice is not within the body
+    /// * ErrorKind::Unsupported for reader and multipart bodies
+    /// * Propagates errors duplicating the file handle
+    pub fn slice(&self, offset: u64, len: u64) -> io::Result<Body> {
+        if offset.checked_add(len).is_none_or(|end| end > self.len()) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Slice out of the bounds of the body",
+            ));
+        }
+
+        match self {
+            Body::Bytes(bytes) => Ok(Body::Bytes(
+                bytes[offset as usize..(offset + len) as usize].to_vec(),
+            )),
+            Body::File {
+                file,
+                offset: start,
+                ..
+            } => Ok(Body::File {
+                file: file.try_clone()?,
+                offset: start + offset,
+                len,
+            }),
+            Body::Reader { .. } | Body::Parts(_) => Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "Only bytes and files can be sliced",
+            )),
+        }
+    }
+
+    /// Read the whole body into memory
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors reading the file or reader,
+    /// ErrorKind::UnexpectedEof if it ends before `len` bytes
+    pub fn into_bytes(mut self) -> io::Result<Vec<u8>> {
+        if let Body::Bytes(bytes) = self {
+            return Ok(bytes);
+        }
+
+        let mut bytes = Vec::with_capacity(self.len() as usize);
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Write the body to `writer`, in chunks of at most CHUNK_SIZE bytes
+    /// if it is not in memory
+    ///
+    /// Files are read from their offset whatever their position, the same
+    /// file may back several parts. The body is left empty once written.
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors reading the body or writing it,
+    /// ErrorKind::UnexpectedEof if a file or reader ends before `len` bytes
+    pub fn write_to<W: Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        match mem::replace(self, Body::Bytes(Vec::new())) {
+            Body::Bytes(bytes) => writer.write_all(&bytes),
+            Body::File {
+                mut file,
+                offset,
+                len,
+            } => {
+                file.seek(SeekFrom::Start(offset))?;
+                copy_chunks(&mut file, writer, len)
+            }
+            Body::Reader { mut reader, len } => copy_chunks(&mut reader, writer, len),
+            Body::Parts(parts) => parts
+                .into_iter()
+                .try_for_each(|mut part| part.write_to(writer)),
+        }
+    }
+
+    /// Write the body to the socket behind `writer`, sending files with sendfile(2)
+    /// on Linux instead of copying them through userspace
+    ///
+    /// `writer` is flushed before a file is sent. Bodies that are not files,
+    /// other platforms and files sendfile(2) does not support use write_to.
+    ///
+    /// # Errors
+    ///
+    /// Same as write_to
+    pub fn send_to(&mut self, writer: &mut BufWriter<&TcpStream>) -> io::Result<()> {
+        match mem::replace(self, Body::Bytes(Vec::new())) {
+            #[cfg(target_os = "linux")]
+            Body::File {
+                mut file,
+                offset,
+                len,
+            } => {
+                writer.flush()?;
+                match sendfile::send_file(&file, offset, len, writer.get_ref()) {
+                    Ok(sent) if sent == len => Ok(()),
+                    Ok(sent) => Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        format!("Body ended {} bytes short", len - sent),
+                    )),
+                    Err(e) if e.kind() == ErrorKind::Unsupported => {
+                        file.seek(SeekFrom::Start(offset))?;
+                        copy_chunks(&mut file, writer, len)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Body::Parts(parts) => parts
+                .into_iter()
+                .try_for_each(|mut part| part.send_to(writer)),
+            mut body => body.write_to(writer),
+        }
+    }
+}
+
+/// Copy exactly `len` bytes from `reader` to `writer` through a CHUNK_SIZE buffer
+fn copy_chunks<R: Read, W: Write>(reader: &mut R, writer: &mut W, len: u64) -> io::Result<()> {
+    let mut buffer = vec![0; CHUNK_SIZE.min(len as usize)];
+    let mut remaining = len;
+
+    while remaining > 0 {
+        let chunk = CHUNK_SIZE.min(remaining as usize);
+        let read = match reader.read(&mut buffer[..chunk]) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!("Body ended {} bytes short", remaining),
+                ))
+            }
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buffer[..read])?;
+        remaining -= read as u64;
+    }
+
+    Ok(())
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Body::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            Body::File { offset, len, .. } => {
+                write!(f, "File({} bytes at offset {})", len, offset)
+            }
+            Body::Reader { len, .. } => write!(f, "Reader({} bytes)", len),
+            Body::Parts(parts) => f.debug_tuple("Parts").field(parts).finish(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Body::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for Body {
+    fn from(bytes: &[u8]) -> Self {
+        Body::Bytes(bytes.to_vec())
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Self {
+        Body::Bytes(text.into_bytes())
+    }
+}
+
+impl From<&str> for Body {
+    fn from(text: &str) -> Self {
+        Body::Bytes(text.as_bytes().to_vec())
+    }
+}
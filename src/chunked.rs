@@ -0,0 +1,314 @@
+//! Chunked transfer-coding decoder
+//!
+//! chunked-body   = *chunk
+//!                  last-chunk
+//!                  trailer-part
+//!                  CRLF
+//!
+//! chunk          = chunk-size [ chunk-ext ] CRLF
+//!                  chunk-data CRLF
+//! chunk-size     = 1*HEXDIG
+//! last-chunk     = 1*("0") [ chunk-ext ] CRLF
+//! chunk-ext      = *( BWS ";" BWS chunk-ext-name [ BWS "=" BWS chunk-ext-val ] )
+//! chunk-ext-val  = token / quoted-string
+
+use std::io::{self, BufRead, Read};
+use std::{error, fmt, str};
+
+use crate::headers::Headers;
+use crate::request::{is_token_char, parse_header};
+
+/// Maximum length of a chunk-size line or a trailer field line, including CRLF
+pub const MAX_LINE_LENGTH: usize = 4096;
+
+/// Maximum number of trailer fields accepted after the last chunk
+pub const MAX_TRAILERS: usize = 64;
+
+/// Returned when the chunked body does not follow the chunked transfer-coding grammar
+#[derive(Debug)]
+pub struct InvalidChunk(String);
+
+impl fmt::Display for InvalidChunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid chunk: {}", self.0)
+    }
+}
+
+impl From<&str> for InvalidChunk {
+    fn from(error: &str) -> Self {
+        InvalidChunk(error.to_string())
+    }
+}
+
+impl error::Error for InvalidChunk {}
+
+/// Decoder state
+enum State {
+    /// Expecting a chunk-size line
+    Size,
+    /// Inside chunk-data, with the number of bytes left in the chunk
+    Data(u64),
+    /// Expecting the CRLF after chunk-data
+    DataEnd,
+    /// Last chunk and trailer-part were read
+    Done,
+}
+
+/// Streaming reader decoding a chunked body
+///
+/// Chunk extensions are validated and ignored, trailer fields are collected
+/// and available through `trailers` once the reader returned end of file.
+///
+/// # Examples
+///
+/// ```
+/// use linda::chunked::ChunkedReader;
+/// use std::io::Read;
+/// # use std::error::Error;
+///
+/// let encoded = b"4;name=value\r\nWiki\r\n5\r\npedia\r\n0\r\nExpires: never\r\n\r\n";
+/// let mut reader = ChunkedReader::new(&encoded[..]);
+///
+/// let mut body = String::new();
+/// reader.read_to_string(&mut body)?;
+///
+/// assert_eq!(body, "Wikipedia");
+/// assert_eq!(reader.trailers().get("expires"), Some("never"));
+///
+/// # Ok::<(), Box<dyn Error>>(())
+/// ```
+pub struct ChunkedReader<R> {
+    reader: R,
+    state: State,
+    trailers: Headers<'static>,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    /// Create a decoder reading the chunked body from `reader`
+    pub fn new(reader: R) -> Self {
+        ChunkedReader {
+            reader,
+            state: State::Size,
+            trailers: Headers::new(),
+        }
+    }
+
+    /// Get the trailer fields, empty until the whole body has been read
+    pub fn trailers(&self) -> &Headers<'static> {
+        &self.trailers
+    }
+
+    /// Consume the decoder, returning the underlying reader and the trailer fields
+    pub fn into_parts(self) -> (R, Headers<'static>) {
+        (self.reader, self.trailers)
+    }
+
+    /// Read a CRLF terminated line, returning it without the CRLF
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = Vec::new();
+        (&mut self.reader)
+            .take(MAX_LINE_LENGTH as u64)
+            .read_until(b'\n', &mut line)?;
+
+        if !line.ends_with(b"\r\n") {
+            return Err(match line.len() {
+                0 => io::ErrorKind::UnexpectedEof.into(),
+                MAX_LINE_LENGTH => invalid("line too long"),
+                _ => invalid("line not terminated by CRLF"),
+            });
+        }
+        line.truncate(line.len() - 2);
+
+        String::from_utf8(line).map_err(|_| invalid("line is not UTF-8"))
+    }
+
+    /// Read the trailer-part and the final CRLF
+    fn read_trailers(&mut self) -> io::Result<()> {
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                return Ok(());
+            }
+
+            if self.trailers.len() == MAX_TRAILERS {
+                return Err(invalid("too many trailer fields"));
+            }
+
+            let (name, value) =
+                parse_header(&line).map_err(|_| invalid("invalid trailer field"))?;
+            self.trailers
+                .try_append(name.to_string(), value.to_string())
+                .map_err(|_| invalid("invalid trailer field"))?;
+        }
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.state {
+                State::Size => {
+                    let size = parse_chunk_size(&self.read_line()?)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+                    if size == 0 {
+                        self.read_trailers()?;
+                        self.state = State::Done;
+                    } else {
+                        self.state = State::Data(size);
+                    }
+                }
+                State::Data(remaining) => {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    let max = buf.len().min(remaining as usize);
+                    let read = self.reader.read(&mut buf[..max])?;
+                    if read == 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+
+                    let remaining = remaining - read as u64;
+                    self.state = if remaining == 0 {
+                        State::DataEnd
+                    } else {
+                        State::Data(remaining)
+                    };
+
+                    return Ok(read);
+                }
+                State::DataEnd => {
+                    if !self.read_line()?.is_empty() {
+                        return Err(invalid("chunk-data longer than chunk-size"));
+                    }
+                    self.state = State::Size;
+                }
+                State::Done => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Parse a chunk-size line, validating and discarding any chunk extensions
+///
+/// # Examples
+///
+/// ```
+/// use linda::chunked::parse_chunk_size;
+///
+/// assert_eq!(parse_chunk_size("1a").unwrap(), 26);
+/// assert_eq!(parse_chunk_size("5 ; name=\"a;b\" ;flag").unwrap(), 5);
+/// assert!(parse_chunk_size("5;name=\"unterminated").is_err());
+/// ```
+///
+/// # Errors
+///
+/// Returns InvalidChunk if:
+/// * chunk-size is empty or not made of hexadecimal digits only
+/// * chunk-size does not fit in 64 bits
+/// * a chunk extension has an empty or invalid name, or an invalid value
+pub fn parse_chunk_size(line: &str) -> Result<u64, InvalidChunk> {
+    let (size, extensions) = match line.find(';') {
+        Some(semicolon) => (&line[..semicolon], Some(&line[semicolon..])),
+        None => (line, None),
+    };
+    // BWS is allowed before the extensions, but not around the digits themselves
+    let size = if extensions.is_some() {
+        size.trim_end_matches([' ', '\t'])
+    } else {
+        size
+    };
+
+    if size.is_empty() || !size.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err(InvalidChunk(format!("chunk-size {:?}", size)));
+    }
+    let size = size
+        .bytes()
+        .try_fold(0u64, |size, digit| {
+            let digit = (digit as char).to_digit(16)? as u64;
+            size.checked_mul(16)?.checked_add(digit)
+        })
+        .ok_or_else(|| InvalidChunk(format!("chunk-size {} too large", size)))?;
+
+    if let Some(extensions) = extensions {
+        if !valid_extensions(extensions.as_bytes()) {
+            return Err(InvalidChunk(format!("chunk-ext {:?}", extensions)));
+        }
+    }
+
+    Ok(size)
+}
+
+/// Whether `extensions`, starting at the first ";", follow the chunk-ext grammar
+fn valid_extensions(mut extensions: &[u8]) -> bool {
+    loop {
+        extensions = trim_bws(extensions);
+        if extensions.is_empty() {
+            return true;
+        }
+
+        let (name, rest) = match extensions.strip_prefix(b";") {
+            Some(rest) => split_token(trim_bws(rest)),
+            None => return false,
+        };
+        if name.is_empty() {
+            return false;
+        }
+
+        extensions = match trim_bws(rest).strip_prefix(b"=").map(trim_bws) {
+            Some(value) if value.starts_with(b"\"") => match skip_quoted_string(value) {
+                Some(rest) => rest,
+                None => return false,
+            },
+            Some(value) => match split_token(value) {
+                (b"", _) => return false,
+                (_, rest) => rest,
+            },
+            None => rest,
+        };
+    }
+}
+
+/// Split the longest token off the start of `input`
+fn split_token(input: &[u8]) -> (&[u8], &[u8]) {
+    let end = input
+        .iter()
+        .position(|&c| !is_token_char(c))
+        .unwrap_or(input.len());
+    input.split_at(end)
+}
+
+/// Skip the quoted-string `input` starts with, returning the bytes after its closing quote
+///
+/// quoted-string = DQUOTE *( qdtext / quoted-pair ) DQUOTE
+/// quoted-pair   = "\" ( HTAB / SP / VCHAR / obs-text )
+fn skip_quoted_string(input: &[u8]) -> Option<&[u8]> {
+    let mut rest = input.strip_prefix(b"\"")?;
+    loop {
+        match *rest {
+            [b'"', ref after @ ..] => return Some(after),
+            [b'\\', c, ref after @ ..] if is_quoted_char(c) => rest = after,
+            [c, ref after @ ..] if is_quoted_char(c) && c != b'\\' => rest = after,
+            _ => return None,
+        }
+    }
+}
+
+/// HTAB, SP, VCHAR or obs-text, the bytes allowed within a quoted-string
+fn is_quoted_char(c: u8) -> bool {
+    c == b'\t' || c == b' ' || (0x21..=0x7e).contains(&c) || c >= 0x80
+}
+
+/// Strip leading optional whitespace
+fn trim_bws(input: &[u8]) -> &[u8] {
+    let start = input
+        .iter()
+        .position(|&c| c != b' ' && c != b'\t')
+        .unwrap_or(input.len());
+    &input[start..]
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, InvalidChunk::from(reason))
+}
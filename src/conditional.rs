@@ -0,0 +1,167 @@
+//! Validators and conditional requests
+//!
+//! ETag          = entity-tag
+//! entity-tag    = [ weak ] opaque-tag
+//! weak          = %x57.2F ; "W/", case-sensitive
+//! opaque-tag    = DQUOTE *etagc DQUOTE
+//!
+//! Preconditions are evaluated in the order of RFC 7232, section 6:
+//! If-Match, If-Unmodified-Since, If-None-Match, If-Modified-Since.
+
+use std::fs::Metadata;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::date::parse_http_date;
+use crate::Method;
+use crate::Request;
+use crate::StatusCode;
+
+/// Compute the entity tag of a file from its size and modification time
+///
+/// The tag is weak if the file was modified less than a second ago,
+/// it could still change again without the modification time changing.
+pub fn etag(metadata: &Metadata) -> String {
+    let modified = metadata.modified().ok();
+    let since_epoch = modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let tag = format!(
+        "\"{:x}-{:x}.{:x}\"",
+        metadata.len(),
+        since_epoch.as_secs(),
+        since_epoch.subsec_nanos()
+    );
+
+    let settled = modified
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= Duration::from_secs(1));
+    if settled {
+        tag
+    } else {
+        format!("W/{}", tag)
+    }
+}
+
+/// Split an If-Match or If-None-Match value into its entity tags
+///
+/// # Examples
+///
+/// ```
+/// use linda::conditional::parse_etags;
+///
+/// assert_eq!(parse_etags(r#""a", W/"b,c" , "d""#), ["\"a\"", "W/\"b,c\"", "\"d\""]);
+/// assert_eq!(parse_etags("*"), ["*"]);
+/// ```
+pub fn parse_etags(value: &str) -> Vec<&str> {
+    let mut etags = Vec::new();
+    let mut rest = value.trim_start_matches([' ', '\t', ',']);
+
+    while !rest.is_empty() {
+        let opaque_start = if rest.starts_with("W/") { 2 } else { 0 };
+        let end = if rest[opaque_start..].starts_with('"') {
+            rest[opaque_start + 1..]
+                .find('"')
+                .map_or(rest.len(), |quote| opaque_start + quote + 2)
+        } else {
+            rest.find(',').unwrap_or(rest.len())
+        };
+
+        etags.push(rest[..end].trim_end());
+        rest = rest[end..].trim_start_matches([' ', '\t', ',']);
+    }
+
+    etags
+}
+
+/// Get the weak version of an entity-tag, e.g. `W/"x"` for `"x"`
+///
+/// # Examples
+///
+/// ```
+/// use linda::conditional::weak;
+///
+/// assert_eq!(weak("\"x\""), "W/\"x\"");
+/// assert_eq!(weak("W/\"x\""), "W/\"x\"");
+/// ```
+pub fn weak(etag: &str) -> String {
+    if etag.starts_with("W/") {
+        etag.to_string()
+    } else {
+        format!("W/{}", etag)
+    }
+}
+
+/// Strong comparison: both tags are strong and their opaque-tags are equal
+pub fn strong_match(a: &str, b: &str) -> bool {
+    !a.starts_with("W/") && !b.starts_with("W/") && a == b
+}
+
+/// Weak comparison: the opaque-tags are equal, weak or not
+pub fn weak_match(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+/// Evaluate the preconditions of `request` against the current validators of a file
+///
+/// Returns the status to answer with instead of the file, None to send it:
+/// * 412 Precondition Failed if If-Match or If-Unmodified-Since fail,
+///   or If-None-Match matches on a method other than GET and HEAD
+/// * 304 Not Modified if If-None-Match or If-Modified-Since show
+///   the client's copy of the file is still current
+pub fn evaluate(request: &Request, etag: &str, modified: Option<SystemTime>) -> Option<StatusCode> {
+    let safe = *request.method() == Method::GET || *request.method() == Method::HEAD;
+
+    if let Some(if_match) = joined(request, "If-Match") {
+        let matches = parse_etags(&if_match)
+            .iter()
+            .any(|tag| *tag == "*" || strong_match(tag, etag));
+        if !matches {
+            return Some(StatusCode::PRECONDITION_FAILED);
+        }
+    } else if let Some(since) = request
+        .header("If-Unmodified-Since")
+        .and_then(parse_http_date)
+    {
+        if modified.is_some_and(|modified| seconds(modified) > seconds(since)) {
+            return Some(StatusCode::PRECONDITION_FAILED);
+        }
+    }
+
+    if let Some(if_none_match) = joined(request, "If-None-Match") {
+        let matches = parse_etags(&if_none_match)
+            .iter()
+            .any(|tag| *tag == "*" || weak_match(tag, etag));
+        if matches {
+            return Some(if safe {
+                StatusCode::NOT_MODIFIED
+            } else {
+                StatusCode::PRECONDITION_FAILED
+            });
+        }
+    } else if let Some(since) = request
+        .header("If-Modified-Since")
+        .and_then(parse_http_date)
+    {
+        if safe && modified.is_some_and(|modified| seconds(modified) <= seconds(since)) {
+            return Some(StatusCode::NOT_MODIFIED);
+        }
+    }
+
+    None
+}
+
+/// Join repeated header fields into one comma separated value, None if absent
+fn joined(request: &Request, name: &str) -> Option<String> {
+    let values = request.headers_all(name);
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.join(", "))
+    }
+}
+
+/// HTTP-dates have a resolution of one second
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
@@ -0,0 +1,530 @@
+//! Server configuration
+
+use log::{info, warn};
+use std::env;
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::content::{ContentType, MimeTypes};
+
+/// Default document root files are served from
+pub const DEFAULT_ROOT: &str = "/var/www";
+
+/// Default maximum accepted request body size (1 MiB)
+pub const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Default maximum Request-Line length in bytes, including CRLF
+pub const DEFAULT_MAX_REQUEST_LINE: usize = 8 * 1024;
+
+/// Default maximum request head size in bytes (Request-Line and header fields)
+pub const DEFAULT_MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// Default time an idle persistent connection is kept open (5 seconds)
+pub const DEFAULT_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default time a write to a client not reading its responses may block (30 seconds)
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default maximum number of requests served on one connection
+pub const DEFAULT_MAX_REQUESTS: usize = 100;
+
+/// Default number of worker threads, each serving one connection at a time
+pub const DEFAULT_WORKERS: usize = 16;
+
+/// Default Server header value
+pub const DEFAULT_SERVER: &str = concat!("Linda/", env!("CARGO_PKG_VERSION"));
+
+/// Default Content-Type of files with an unknown or missing extension
+pub const DEFAULT_CONTENT_TYPE: ContentType = ContentType::OCTET_STREAM;
+
+/// Default index files tried, in order, when a directory is requested
+pub const DEFAULT_INDEX_FILES: [&str; 2] = ["index.html", "index.htm"];
+
+/// Default minimum body size in bytes for responses to be compressed
+pub const DEFAULT_COMPRESSION_MIN_SIZE: usize = 1024;
+
+/// Default charset parameter added to textual Content-Types
+pub const DEFAULT_CHARSET: &str = "utf-8";
+
+/// Server configuration shared by every connection
+///
+/// # Examples
+///
+/// ```
+/// use linda::config::Config;
+///
+/// let mut config = Config::new();
+/// config.max_body_size_mut(64 * 1024);
+///
+/// assert_eq!(config.max_body_size(), 64 * 1024);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    root: String,
+    max_body_size: usize,
+    max_request_line: usize,
+    max_head_size: usize,
+    keep_alive_timeout: Duration,
+    write_timeout: Duration,
+    max_requests: usize,
+    workers: usize,
+    server: Option<String>,
+    default_content_type: ContentType,
+    default_charset: Option<String>,
+    sniff_content_type: bool,
+    index_files: Vec<String>,
+    autoindex: bool,
+    serve_hidden: bool,
+    compression: bool,
+    compression_min_size: usize,
+    precompressed: bool,
+    zero_copy: bool,
+    mime_types: MimeTypes,
+}
+
+impl Config {
+    /// Create a new Config object with defaults:
+    ///
+    /// root: DEFAULT_ROOT
+    /// max_body_size: DEFAULT_MAX_BODY_SIZE
+    /// max_request_line: DEFAULT_MAX_REQUEST_LINE
+    /// max_head_size: DEFAULT_MAX_HEAD_SIZE
+    /// keep_alive_timeout: DEFAULT_KEEP_ALIVE_TIMEOUT
+    /// write_timeout: DEFAULT_WRITE_TIMEOUT
+    /// max_requests: DEFAULT_MAX_REQUESTS
+    /// workers: DEFAULT_WORKERS
+    /// server: DEFAULT_SERVER
+    /// default_content_type: DEFAULT_CONTENT_TYPE
+    /// default_charset: DEFAULT_CHARSET
+    /// sniff_content_type: false
+    /// index_files: DEFAULT_INDEX_FILES
+    /// autoindex: false
+    /// serve_hidden: false
+    /// compression: true
+    /// compression_min_size: DEFAULT_COMPRESSION_MIN_SIZE
+    /// precompressed: true
+    /// zero_copy: true
+    /// mime_types: the built-in table, see MimeTypes
+    pub fn new() -> Self {
+        Config::default()
+    }
+
+    /// Create a Config from the environment, falling back to the defaults
+    ///
+    /// * `LINDA_ROOT`: document root files are served from
+    /// * `LINDA_MAX_BODY_SIZE`: maximum request body size in bytes
+    /// * `LINDA_MAX_REQUEST_LINE`: maximum Request-Line length in bytes
+    /// * `LINDA_MAX_HEAD_SIZE`: maximum request head size in bytes
+    /// * `LINDA_KEEP_ALIVE_TIMEOUT`: idle connection timeout in seconds, at least 1
+    /// * `LINDA_WRITE_TIMEOUT`: timeout in seconds for writes to a client, at least 1
+    /// * `LINDA_MAX_REQUESTS`: maximum number of requests per connection, at least 1
+    /// * `LINDA_WORKERS`: number of worker threads, at least 1
+    /// * `LINDA_SERVER`: Server header value, empty to leave the header out
+    /// * `LINDA_DEFAULT_CONTENT_TYPE`: Content-Type of files with an unknown extension
+    /// * `LINDA_DEFAULT_CHARSET`: charset of textual files, empty to leave it out
+    /// * `LINDA_SNIFF_CONTENT_TYPE`: `true` to sniff the type of files with an unknown extension
+    /// * `LINDA_INDEX_FILES`: comma separated index file names, e.g. `index.html,index.htm`
+    /// * `LINDA_AUTOINDEX`: `true` to list directories without an index file
+    /// * `LINDA_SERVE_HIDDEN`: `true` to serve files and directories starting with `.`
+    /// * `LINDA_COMPRESSION`: `false` to never compress response bodies
+    /// * `LINDA_COMPRESSION_MIN_SIZE`: minimum body size in bytes for compression
+    /// * `LINDA_PRECOMPRESSED`: `false` to ignore `.br`, `.zst` and `.gz` siblings of files
+    /// * `LINDA_ZERO_COPY`: `false` to copy files through userspace instead of using sendfile(2)
+    /// * `LINDA_MIME_TYPES`: path of an Apache-style `mime.types` file to load
+    /// * `LINDA_MIME_OVERRIDES`: extra mappings such as `md=text/markdown,log=text/plain`,
+    ///   taking precedence over the `mime.types` file
+    pub fn from_env() -> Self {
+        let mut config = Config::new();
+
+        if let Ok(root) = env::var("LINDA_ROOT") {
+            config.root_mut(root);
+        }
+        if let Some(max_body_size) = env_var("LINDA_MAX_BODY_SIZE") {
+            config.max_body_size_mut(max_body_size);
+        }
+        if let Some(max_request_line) = env_var("LINDA_MAX_REQUEST_LINE") {
+            config.max_request_line_mut(max_request_line);
+        }
+        if let Some(max_head_size) = env_var("LINDA_MAX_HEAD_SIZE") {
+            config.max_head_size_mut(max_head_size);
+        }
+        // Zero is rejected when parsing, like any other invalid value
+        if let Some(seconds) = env_var::<NonZeroU64>("LINDA_KEEP_ALIVE_TIMEOUT") {
+            config.keep_alive_timeout_mut(Duration::from_secs(seconds.get()));
+        }
+        if let Some(seconds) = env_var::<NonZeroU64>("LINDA_WRITE_TIMEOUT") {
+            config.write_timeout_mut(Duration::from_secs(seconds.get()));
+        }
+        if let Some(max_requests) = env_var::<NonZeroUsize>("LINDA_MAX_REQUESTS") {
+            config.max_requests_mut(max_requests.get());
+        }
+        if let Some(workers) = env_var::<NonZeroUsize>("LINDA_WORKERS") {
+            config.workers_mut(workers.get());
+        }
+        if let Ok(server) = env::var("LINDA_SERVER") {
+            config.server_mut(Some(server).filter(|server| !server.is_empty()));
+        }
+        if let Some(default_content_type) = env_var("LINDA_DEFAULT_CONTENT_TYPE") {
+            config.default_content_type_mut(default_content_type);
+        }
+        if let Ok(charset) = env::var("LINDA_DEFAULT_CHARSET") {
+            config.default_charset_mut(Some(charset).filter(|charset| !charset.is_empty()));
+        }
+        if let Some(sniff_content_type) = env_var("LINDA_SNIFF_CONTENT_TYPE") {
+            config.sniff_content_type_mut(sniff_content_type);
+        }
+        if let Ok(index_files) = env::var("LINDA_INDEX_FILES") {
+            config.index_files_mut(
+                index_files
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|index| !index.is_empty())
+                    .map(String::from)
+                    .collect(),
+            );
+        }
+        if let Some(autoindex) = env_var("LINDA_AUTOINDEX") {
+            config.autoindex_mut(autoindex);
+        }
+        if let Some(serve_hidden) = env_var("LINDA_SERVE_HIDDEN") {
+            config.serve_hidden_mut(serve_hidden);
+        }
+        if let Some(compression) = env_var("LINDA_COMPRESSION") {
+            config.compression_mut(compression);
+        }
+        if let Some(compression_min_size) = env_var("LINDA_COMPRESSION_MIN_SIZE") {
+            config.compression_min_size_mut(compression_min_size);
+        }
+        if let Some(precompressed) = env_var("LINDA_PRECOMPRESSED") {
+            config.precompressed_mut(precompressed);
+        }
+        if let Some(zero_copy) = env_var("LINDA_ZERO_COPY") {
+            config.zero_copy_mut(zero_copy);
+        }
+        if let Ok(path) = env::var("LINDA_MIME_TYPES") {
+            match config.mime_types_mut().load_file(&path) {
+                Ok(mapped) => info!("Loaded {} MIME types from {}", mapped, path),
+                Err(e) => warn!("Ignoring LINDA_MIME_TYPES {}: {}", path, e),
+            }
+        }
+        if let Ok(overrides) = env::var("LINDA_MIME_OVERRIDES") {
+            if let Err(e) = config.mime_types_mut().insert_list(&overrides) {
+                warn!("Ignoring invalid LINDA_MIME_OVERRIDES entry: {}", e);
+            }
+        }
+
+        config
+    }
+
+    /// Get the document root files are served from
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Set the document root files are served from
+    pub fn root_mut(&mut self, root: String) -> &mut Self {
+        self.root = root;
+        self
+    }
+
+    /// Get the maximum request body size in bytes
+    pub fn max_body_size(&self) -> usize {
+        self.max_body_size
+    }
+
+    /// Set the maximum request body size in bytes
+    ///
+    /// Requests announcing a larger body are answered with 413
+    pub fn max_body_size_mut(&mut self, max_body_size: usize) -> &mut Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Get the maximum Request-Line length in bytes
+    pub fn max_request_line(&self) -> usize {
+        self.max_request_line
+    }
+
+    /// Set the maximum Request-Line length in bytes
+    ///
+    /// Requests with a longer Request-Line are answered with 414
+    pub fn max_request_line_mut(&mut self, max_request_line: usize) -> &mut Self {
+        self.max_request_line = max_request_line;
+        self
+    }
+
+    /// Get the maximum request head size in bytes
+    pub fn max_head_size(&self) -> usize {
+        self.max_head_size
+    }
+
+    /// Set the maximum request head size in bytes
+    ///
+    /// Requests with a larger head are answered with 431
+    pub fn max_head_size_mut(&mut self, max_head_size: usize) -> &mut Self {
+        self.max_head_size = max_head_size;
+        self
+    }
+
+    /// Get the time an idle persistent connection is kept open
+    pub fn keep_alive_timeout(&self) -> Duration {
+        self.keep_alive_timeout
+    }
+
+    /// Set the time an idle persistent connection is kept open
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timeout is zero, sockets do not accept a zero read timeout
+    pub fn keep_alive_timeout_mut(&mut self, keep_alive_timeout: Duration) -> &mut Self {
+        assert!(keep_alive_timeout > Duration::from_secs(0));
+        self.keep_alive_timeout = keep_alive_timeout;
+        self
+    }
+
+    /// Get the time a write to the client may block
+    pub fn write_timeout(&self) -> Duration {
+        self.write_timeout
+    }
+
+    /// Set the time a write to the client may block
+    ///
+    /// A client that stops reading its responses is disconnected once it expires,
+    /// instead of holding its worker forever.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timeout is zero, sockets do not accept a zero write timeout
+    pub fn write_timeout_mut(&mut self, write_timeout: Duration) -> &mut Self {
+        assert!(write_timeout > Duration::from_secs(0));
+        self.write_timeout = write_timeout;
+        self
+    }
+
+    /// Get the maximum number of requests served on one connection
+    pub fn max_requests(&self) -> usize {
+        self.max_requests
+    }
+
+    /// Set the maximum number of requests served on one connection
+    ///
+    /// The response to the last allowed request carries `Connection: close`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the maximum is zero, no request would ever be served
+    pub fn max_requests_mut(&mut self, max_requests: usize) -> &mut Self {
+        assert!(max_requests > 0);
+        self.max_requests = max_requests;
+        self
+    }
+
+    /// Get the number of worker threads
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Set the number of worker threads, each serving one connection at a time
+    ///
+    /// Persistent connections keep their worker while idle, unless connections
+    /// are waiting for one, see handle_connection_with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number is zero, no connection would ever be served
+    pub fn workers_mut(&mut self, workers: usize) -> &mut Self {
+        assert!(workers > 0);
+        self.workers = workers;
+        self
+    }
+
+    /// Get the Server header value, None if the header is left out
+    pub fn server(&self) -> Option<&str> {
+        self.server.as_deref()
+    }
+
+    /// Set the Server header value, None to leave the header out
+    pub fn server_mut(&mut self, server: Option<String>) -> &mut Self {
+        self.server = server;
+        self
+    }
+
+    /// Get the Content-Type of files with an unknown or missing extension
+    pub fn default_content_type(&self) -> &ContentType {
+        &self.default_content_type
+    }
+
+    /// Set the Content-Type of files with an unknown or missing extension
+    pub fn default_content_type_mut(&mut self, default_content_type: ContentType) -> &mut Self {
+        self.default_content_type = default_content_type;
+        self
+    }
+
+    /// Get the charset added to textual Content-Types, None if it is left out
+    pub fn default_charset(&self) -> Option<&str> {
+        self.default_charset.as_deref()
+    }
+
+    /// Set the charset added to textual Content-Types, None to leave it out
+    ///
+    /// Applies to `text/*`, JSON, XML and JavaScript files without a charset of their own
+    pub fn default_charset_mut(&mut self, default_charset: Option<String>) -> &mut Self {
+        self.default_charset = default_charset;
+        self
+    }
+
+    /// Whether the type of files with an unknown or missing extension is sniffed
+    pub fn sniff_content_type(&self) -> bool {
+        self.sniff_content_type
+    }
+
+    /// Set whether the type of files with an unknown or missing extension is sniffed
+    /// from their leading bytes, before falling back to the default Content-Type
+    pub fn sniff_content_type_mut(&mut self, sniff_content_type: bool) -> &mut Self {
+        self.sniff_content_type = sniff_content_type;
+        self
+    }
+
+    /// Get the index files tried, in order, when a directory is requested
+    pub fn index_files(&self) -> &[String] {
+        &self.index_files
+    }
+
+    /// Set the index files tried, in order, when a directory is requested
+    ///
+    /// Directories without any of them are answered with 404, unless autoindex is on
+    pub fn index_files_mut(&mut self, index_files: Vec<String>) -> &mut Self {
+        self.index_files = index_files;
+        self
+    }
+
+    /// Whether directories without an index file are listed
+    pub fn autoindex(&self) -> bool {
+        self.autoindex
+    }
+
+    /// Set whether directories without an index file are listed, see autoindex
+    ///
+    /// The listing is HTML, or JSON for clients preferring `application/json`
+    pub fn autoindex_mut(&mut self, autoindex: bool) -> &mut Self {
+        self.autoindex = autoindex;
+        self
+    }
+
+    /// Whether hidden files and directories, starting with `.`, are served
+    pub fn serve_hidden(&self) -> bool {
+        self.serve_hidden
+    }
+
+    /// Set whether hidden files and directories are served, see serve_hidden
+    ///
+    /// When off, paths such as `/.git/config` or `/.env` are answered with 404.
+    /// `/.well-known/` (RFC 8615) is served either way. Listings never show
+    /// hidden entries.
+    pub fn serve_hidden_mut(&mut self, serve_hidden: bool) -> &mut Self {
+        self.serve_hidden = serve_hidden;
+        self
+    }
+
+    /// Whether response bodies are compressed for clients accepting gzip or deflate
+    pub fn compression(&self) -> bool {
+        self.compression
+    }
+
+    /// Set whether response bodies are compressed, see Response::compress_mut
+    pub fn compression_mut(&mut self, compression: bool) -> &mut Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Get the minimum body size in bytes for responses to be compressed
+    pub fn compression_min_size(&self) -> usize {
+        self.compression_min_size
+    }
+
+    /// Set the minimum body size in bytes for responses to be compressed
+    ///
+    /// Compressing small bodies costs more time than it saves
+    pub fn compression_min_size_mut(&mut self, compression_min_size: usize) -> &mut Self {
+        self.compression_min_size = compression_min_size;
+        self
+    }
+
+    /// Whether precompressed siblings of files, e.g. `app.css.gz`, are served
+    /// to clients accepting their encoding
+    pub fn precompressed(&self) -> bool {
+        self.precompressed
+    }
+
+    /// Set whether precompressed siblings of files are served
+    pub fn precompressed_mut(&mut self, precompressed: bool) -> &mut Self {
+        self.precompressed = precompressed;
+        self
+    }
+
+    /// Whether file bodies are sent with sendfile(2) where available
+    pub fn zero_copy(&self) -> bool {
+        self.zero_copy
+    }
+
+    /// Set whether file bodies are sent with sendfile(2) where available,
+    /// instead of being copied through a userspace buffer
+    pub fn zero_copy_mut(&mut self, zero_copy: bool) -> &mut Self {
+        self.zero_copy = zero_copy;
+        self
+    }
+
+    /// Get the registry mapping file extensions to Content-Types
+    pub fn mime_types(&self) -> &MimeTypes {
+        &self.mime_types
+    }
+
+    /// Get a mutable reference to the registry, e.g. to load a `mime.types` file
+    pub fn mime_types_mut(&mut self) -> &mut MimeTypes {
+        &mut self.mime_types
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            root: DEFAULT_ROOT.to_string(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            max_request_line: DEFAULT_MAX_REQUEST_LINE,
+            max_head_size: DEFAULT_MAX_HEAD_SIZE,
+            keep_alive_timeout: DEFAULT_KEEP_ALIVE_TIMEOUT,
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            max_requests: DEFAULT_MAX_REQUESTS,
+            workers: DEFAULT_WORKERS,
+            server: Some(DEFAULT_SERVER.to_string()),
+            default_content_type: DEFAULT_CONTENT_TYPE,
+            default_charset: Some(DEFAULT_CHARSET.to_string()),
+            sniff_content_type: false,
+            index_files: DEFAULT_INDEX_FILES
+                .iter()
+                .map(|index| index.to_string())
+                .collect(),
+            autoindex: false,
+            serve_hidden: false,
+            compression: true,
+            compression_min_size: DEFAULT_COMPRESSION_MIN_SIZE,
+            precompressed: true,
+            zero_copy: true,
+            mime_types: MimeTypes::new(),
+        }
+    }
+}
+
+/// Read and parse an environment variable, warning about (and ignoring) invalid values
+fn env_var<T: FromStr>(name: &str) -> Option<T> {
+    let value = env::var(name).ok()?;
+
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            warn!("Ignoring invalid value for {}: {}", name, value);
+            None
+        }
+    }
+}
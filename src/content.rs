@@ -1,8 +1,17 @@
 //! HTTP Content (MIME) Types
+//!
+//! media-type = type "/" subtype *( OWS ";" OWS parameter )
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
 use std::{error, fmt};
 
+use crate::request::is_token_char;
+
 /// Whenever an unsupported/invalid content type gets requested
 #[derive(Debug)]
 pub struct InvalidContentType(String);
@@ -20,64 +29,576 @@ impl From<&str> for InvalidContentType {
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Copy, Clone)]
-pub(super) enum ContentType {
-    CSS,
-    HTML,
-    GIF,
-    PNG,
-    JPEG,
-    TEXT,
-    SVG,
-    XML,
-    PDF,
-    ICO,
-}
+/// Built-in extension to media type table, extensions are lowercase
+const BUILTIN_TYPES: &[(&str, &str)] = &[
+    // Text
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("ics", "text/calendar"),
+    ("js", "text/javascript"),
+    ("md", "text/markdown"),
+    ("mjs", "text/javascript"),
+    ("txt", "text/plain"),
+    ("vtt", "text/vtt"),
+    // Images
+    ("apng", "image/apng"),
+    ("avif", "image/avif"),
+    ("bmp", "image/bmp"),
+    ("gif", "image/gif"),
+    ("ico", "image/x-icon"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("webp", "image/webp"),
+    // Fonts
+    ("otf", "font/otf"),
+    ("ttf", "font/ttf"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    // Audio and video
+    ("aac", "audio/aac"),
+    ("flac", "audio/flac"),
+    ("m4a", "audio/mp4"),
+    ("mp3", "audio/mpeg"),
+    ("oga", "audio/ogg"),
+    ("ogg", "audio/ogg"),
+    ("opus", "audio/opus"),
+    ("wav", "audio/wav"),
+    ("weba", "audio/webm"),
+    ("avi", "video/x-msvideo"),
+    ("m4v", "video/mp4"),
+    ("mov", "video/quicktime"),
+    ("mp4", "video/mp4"),
+    ("mpeg", "video/mpeg"),
+    ("ogv", "video/ogg"),
+    ("webm", "video/webm"),
+    // Applications
+    ("7z", "application/x-7z-compressed"),
+    ("atom", "application/atom+xml"),
+    ("bin", "application/octet-stream"),
+    ("bz2", "application/x-bzip2"),
+    ("doc", "application/msword"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    ("epub", "application/epub+zip"),
+    ("gz", "application/gzip"),
+    ("jar", "application/java-archive"),
+    ("json", "application/json"),
+    ("jsonld", "application/ld+json"),
+    ("map", "application/json"),
+    ("odp", "application/vnd.oasis.opendocument.presentation"),
+    ("ods", "application/vnd.oasis.opendocument.spreadsheet"),
+    ("odt", "application/vnd.oasis.opendocument.text"),
+    ("pdf", "application/pdf"),
+    ("ppt", "application/vnd.ms-powerpoint"),
+    (
+        "pptx",
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    ),
+    ("rar", "application/vnd.rar"),
+    ("rss", "application/rss+xml"),
+    ("rtf", "application/rtf"),
+    ("tar", "application/x-tar"),
+    ("wasm", "application/wasm"),
+    ("webmanifest", "application/manifest+json"),
+    ("xhtml", "application/xhtml+xml"),
+    ("xls", "application/vnd.ms-excel"),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ),
+    ("xml", "application/xml"),
+    ("xz", "application/x-xz"),
+    ("zip", "application/zip"),
+    ("zst", "application/zstd"),
+];
+
+/// Number of leading bytes inspected by ContentType::sniff
+pub const SNIFF_LENGTH: usize = 512;
+
+/// Magic numbers of binary formats recognised by ContentType::sniff
+const SIGNATURES: &[(&[u8], ContentType)] = &[
+    (b"\x89PNG\r\n\x1a\n", ContentType::PNG),
+    (b"\xff\xd8\xff", ContentType::JPEG),
+    (b"GIF87a", ContentType::GIF),
+    (b"GIF89a", ContentType::GIF),
+    (b"%PDF-", ContentType::PDF),
+];
+
+/// Case-insensitive prefixes marking the start of an HTML document
+const HTML_MARKERS: &[&[u8]] = &[
+    b"<!doctype html",
+    b"<html",
+    b"<head",
+    b"<body",
+    b"<title",
+    b"<script",
+    b"<!--",
+];
+
+/// A media type with optional parameters, e.g. `text/html; charset=utf-8`
+///
+/// Constants cover the common types, any other valid media type can be created with `new`.
+///
+/// # Examples
+///
+/// ```
+/// use linda::content::ContentType;
+/// # use std::error::Error;
+///
+/// let html = ContentType::HTML.with_charset("utf-8");
+///
+/// assert_eq!(html.as_str(), "text/html; charset=utf-8");
+/// assert_eq!(html.essence(), "text/html");
+/// assert_eq!(html.param("Charset"), Some("utf-8"));
+/// assert!(html.is_textual());
+/// assert!(!ContentType::PNG.is_textual());
+///
+/// # Ok::<(), Box<dyn Error>>(())
+/// ```
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub struct ContentType(Cow<'static, str>);
 
 impl ContentType {
-    pub(super) fn from_ext_str(ext: &str) -> Result<ContentType, InvalidContentType> {
-        let content_types: HashMap<&str, ContentType> = [
-            ("css", ContentType::CSS),
-            ("gif", ContentType::GIF),
-            ("htm", ContentType::HTML),
-            ("html", ContentType::HTML),
-            ("jpeg", ContentType::JPEG),
-            ("jpg", ContentType::JPEG),
-            ("png", ContentType::PNG),
-            ("svg", ContentType::SVG),
-            ("txt", ContentType::TEXT),
-            ("xml", ContentType::XML),
-            ("pdf", ContentType::PDF),
-            ("ico", ContentType::ICO),
-        ]
-        .iter()
-        .cloned()
-        .collect();
-        if let Some(content_type) = content_types.get(ext) {
-            Ok(*content_type)
+    pub const CSS: ContentType = ContentType(Cow::Borrowed("text/css"));
+    pub const CSV: ContentType = ContentType(Cow::Borrowed("text/csv"));
+    pub const HTML: ContentType = ContentType(Cow::Borrowed("text/html"));
+    pub const JAVASCRIPT: ContentType = ContentType(Cow::Borrowed("text/javascript"));
+    pub const TEXT: ContentType = ContentType(Cow::Borrowed("text/plain"));
+    pub const AVIF: ContentType = ContentType(Cow::Borrowed("image/avif"));
+    pub const GIF: ContentType = ContentType(Cow::Borrowed("image/gif"));
+    pub const ICO: ContentType = ContentType(Cow::Borrowed("image/x-icon"));
+    pub const JPEG: ContentType = ContentType(Cow::Borrowed("image/jpeg"));
+    pub const PNG: ContentType = ContentType(Cow::Borrowed("image/png"));
+    pub const SVG: ContentType = ContentType(Cow::Borrowed("image/svg+xml"));
+    pub const WEBP: ContentType = ContentType(Cow::Borrowed("image/webp"));
+    pub const WOFF: ContentType = ContentType(Cow::Borrowed("font/woff"));
+    pub const WOFF2: ContentType = ContentType(Cow::Borrowed("font/woff2"));
+    pub const MP4: ContentType = ContentType(Cow::Borrowed("video/mp4"));
+    pub const WEBM: ContentType = ContentType(Cow::Borrowed("video/webm"));
+    pub const JSON: ContentType = ContentType(Cow::Borrowed("application/json"));
+    pub const OCTET_STREAM: ContentType = ContentType(Cow::Borrowed("application/octet-stream"));
+    pub const PDF: ContentType = ContentType(Cow::Borrowed("application/pdf"));
+    pub const WASM: ContentType = ContentType(Cow::Borrowed("application/wasm"));
+    pub const XML: ContentType = ContentType(Cow::Borrowed("application/xml"));
+    pub const ZIP: ContentType = ContentType(Cow::Borrowed("application/zip"));
+
+    /// Create a ContentType from a media type such as `text/html; charset=utf-8`
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidContentType if type or subtype is not a token,
+    /// or if the parameters contain control characters
+    pub fn new(media_type: &str) -> Result<ContentType, InvalidContentType> {
+        let media_type = media_type.trim();
+        let essence = media_type.split(';').next().unwrap_or("").trim_end();
+
+        let valid_essence = match essence.find('/') {
+            Some(slash) => is_token(&essence[..slash]) && is_token(&essence[slash + 1..]),
+            None => false,
+        };
+        if !valid_essence || media_type.chars().any(|c| c.is_control()) {
+            return Err(InvalidContentType::from(media_type));
+        }
+
+        Ok(ContentType(Cow::Owned(media_type.to_string())))
+    }
+
+    /// Look up the built-in media type of a file extension, without the leading `.`
+    ///
+    /// The lookup is case-sensitive, see MimeTypes for a configurable registry.
+    pub fn from_ext_str(ext: &str) -> Result<ContentType, InvalidContentType> {
+        BUILTIN_TYPES
+            .iter()
+            .find(|(builtin, _)| *builtin == ext)
+            .map(|(_, media_type)| ContentType(Cow::Borrowed(media_type)))
+            .ok_or_else(|| InvalidContentType::from(ext))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get the media type without parameters, e.g. `text/html`
+    pub fn essence(&self) -> &str {
+        self.0.split(';').next().unwrap_or("").trim_end()
+    }
+
+    /// Iterate over the (name, value) parameters, quotes around values are removed
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.split(';').skip(1).filter_map(|param| {
+            let equals = param.find('=')?;
+            let value = param[equals + 1..].trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+
+            Some((param[..equals].trim(), value))
+        })
+    }
+
+    /// Get the value of a parameter, the name is case-insensitive
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// Return this media type with the parameter `name` set to `value`,
+    /// replacing any previous value
+    ///
+    /// Values that are not a token are quoted.
+    pub fn with_param(&self, name: &str, value: &str) -> ContentType {
+        let mut media_type = self.essence().to_string();
+
+        for (param, old_value) in self.params() {
+            if !param.eq_ignore_ascii_case(name) {
+                media_type.push_str(&format!("; {}={}", param, quote(old_value)));
+            }
+        }
+        media_type.push_str(&format!("; {}={}", name, quote(value)));
+
+        ContentType(Cow::Owned(media_type))
+    }
+
+    /// Return this media type with a `charset` parameter
+    pub fn with_charset(&self, charset: &str) -> ContentType {
+        self.with_param("charset", charset)
+    }
+
+    /// Whether the media type is text a charset applies to:
+    /// `text/*`, JSON, XML and JavaScript
+    pub fn is_textual(&self) -> bool {
+        let essence = self.essence().to_ascii_lowercase();
+        let subtype = essence.split('/').nth(1).unwrap_or("");
+
+        essence.starts_with("text/")
+            || subtype == "json"
+            || subtype == "xml"
+            || subtype == "javascript"
+            || subtype == "ecmascript"
+            || subtype.ends_with("+json")
+            || subtype.ends_with("+xml")
+    }
+
+    /// Whether compressing a body of this media type is worthwhile
+    ///
+    /// Textual types and a few uncompressed binary formats are,
+    /// already compressed formats such as PNG, JPEG or ZIP are not.
+    pub fn is_compressible(&self) -> bool {
+        const UNCOMPRESSED: [&str; 7] = [
+            "image/svg+xml",
+            "image/x-icon",
+            "image/bmp",
+            "application/wasm",
+            "application/rtf",
+            "font/ttf",
+            "font/otf",
+        ];
+
+        self.is_textual() || UNCOMPRESSED.contains(&self.essence().to_ascii_lowercase().as_str())
+    }
+
+    /// Get the quality value the Accept header values give this media type,
+    /// 0 if it is not acceptable
+    ///
+    /// The most specific matching media range wins: `type/subtype` over `type/*` over `*/*`.
+    /// Without any Accept header everything is acceptable with quality 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use linda::content::ContentType;
+    ///
+    /// let accept = ["text/html,application/xhtml+xml,*/*;q=0.8"];
+    ///
+    /// assert_eq!(ContentType::HTML.quality(&accept), 1.0);
+    /// assert_eq!(ContentType::JSON.quality(&accept), 0.8);
+    /// assert_eq!(ContentType::JSON.quality(&["text/*"]), 0.0);
+    /// assert_eq!(ContentType::JSON.quality(&[]), 1.0);
+    /// ```
+    pub fn quality(&self, accept: &[&str]) -> f32 {
+        self.preference(accept).0
+    }
+
+    /// Rank this media type by the Accept header values, as its quality and then
+    /// the specificity of the matching media range
+    ///
+    /// The specificity is 2 for `type/subtype`, 1 for `type/*`, and 0 for `*/*`,
+    /// no Accept header or when the type is not acceptable. Compare the pairs to choose between
+    /// representations of equal quality, preferring the one named explicitly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use linda::content::ContentType;
+    ///
+    /// let accept = ["application/json, text/plain, */*"];
+    ///
+    /// assert_eq!(ContentType::JSON.preference(&accept), (1.0, 2));
+    /// assert_eq!(ContentType::HTML.preference(&accept), (1.0, 0));
+    /// assert!(ContentType::JSON.preference(&accept) > ContentType::HTML.preference(&accept));
+    /// assert_eq!(ContentType::JSON.preference(&["application/json;q=0"]), (0.0, 0));
+    /// ```
+    pub fn preference(&self, accept: &[&str]) -> (f32, u8) {
+        if accept.is_empty() {
+            return (1.0, 0);
+        }
+
+        let essence = self.essence().to_ascii_lowercase();
+        let main_type = essence.split('/').next().unwrap_or("");
+        let mut best: Option<(u8, f32)> = None;
+
+        for range in accept.iter().flat_map(|value| value.split(',')) {
+            let mut parts = range.split(';');
+            let media_range = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+
+            let specificity = if media_range == essence {
+                2
+            } else if media_range == format!("{}/*", main_type) {
+                1
+            } else if media_range == "*/*" {
+                0
+            } else {
+                continue;
+            };
+            let quality = parts
+                .filter_map(|param| {
+                    let (name, value) = param.split_at(param.find('=')?);
+                    Some((name.trim(), value[1..].trim()))
+                })
+                .find(|(name, _)| name.eq_ignore_ascii_case("q"))
+                .and_then(|(_, value)| value.parse::<f32>().ok())
+                .map_or(1.0, |quality| quality.clamp(0.0, 1.0));
+
+            if best.is_none_or(|(best, _)| specificity > best) {
+                best = Some((specificity, quality));
+            }
+        }
+
+        match best {
+            Some((specificity, quality)) if quality > 0.0 => (quality, specificity),
+            _ => (0.0, 0),
+        }
+    }
+
+    /// Guess the media type of a file from its leading bytes
+    ///
+    /// Recognises PNG, JPEG, GIF and PDF magic numbers, HTML documents and UTF-8 text.
+    /// Only the first SNIFF_LENGTH bytes are looked at, None if nothing matched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use linda::content::ContentType;
+    ///
+    /// assert_eq!(ContentType::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some(ContentType::PNG));
+    /// assert_eq!(ContentType::sniff(b"\n  <!DOCTYPE html><p>Hi"), Some(ContentType::HTML));
+    /// assert_eq!(ContentType::sniff("Grüße\n".as_bytes()), Some(ContentType::TEXT));
+    /// assert_eq!(ContentType::sniff(b"\0\x01\x02binary"), None);
+    /// ```
+    pub fn sniff(bytes: &[u8]) -> Option<ContentType> {
+        let bytes = &bytes[..bytes.len().min(SNIFF_LENGTH)];
+
+        if let Some((_, content_type)) = SIGNATURES
+            .iter()
+            .find(|(signature, _)| bytes.starts_with(signature))
+        {
+            return Some(content_type.clone());
+        }
+
+        if !is_text(bytes) {
+            return None;
+        }
+
+        let start = bytes
+            .iter()
+            .position(|c| !c.is_ascii_whitespace())
+            .unwrap_or(bytes.len());
+        let is_html = HTML_MARKERS.iter().any(|marker| {
+            bytes[start..]
+                .get(..marker.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(marker))
+        });
+
+        Some(if is_html {
+            ContentType::HTML
         } else {
-            Err(InvalidContentType(ext.to_string()))
+            ContentType::TEXT
+        })
+    }
+}
+
+/// Whether `bytes` look like UTF-8 text: valid UTF-8 without binary control characters
+///
+/// A multi-byte character cut off at the end of `bytes` is allowed.
+fn is_text(bytes: &[u8]) -> bool {
+    if bytes.is_empty() {
+        return false;
+    }
+
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or("")
         }
+        Err(_) => return false,
+    };
+
+    !text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b'))
+}
+
+impl FromStr for ContentType {
+    type Err = InvalidContentType;
+
+    fn from_str(media_type: &str) -> Result<ContentType, InvalidContentType> {
+        ContentType::new(media_type)
     }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Registry mapping file extensions to media types
+///
+/// Starts out with the built-in table. Apache-style `mime.types` files and
+/// single extensions added afterwards take precedence over earlier entries.
+///
+/// # Examples
+///
+/// ```
+/// use linda::content::{ContentType, MimeTypes};
+/// # use std::error::Error;
+///
+/// let mut mime_types = MimeTypes::new();
+/// mime_types.load(&b"text/x-rust rs\n# comment\n"[..])?;
+/// mime_types.insert("md", ContentType::new("text/plain")?);
+///
+/// assert_eq!(mime_types.get("RS"), Some(&ContentType::new("text/x-rust")?));
+/// assert_eq!(mime_types.get("md"), Some(&ContentType::TEXT));
+/// assert_eq!(mime_types.get("wasm"), Some(&ContentType::WASM));
+/// assert_eq!(mime_types.get("unknown"), None);
+///
+/// # Ok::<(), Box<dyn Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct MimeTypes {
+    types: HashMap<String, ContentType>,
+}
+
+impl MimeTypes {
+    /// Create a registry holding the built-in table
+    pub fn new() -> Self {
+        MimeTypes::default()
+    }
+
+    /// Get the media type of a file extension, the extension is case-insensitive
+    pub fn get(&self, ext: &str) -> Option<&ContentType> {
+        self.types.get(&ext.to_ascii_lowercase())
+    }
+
+    /// Get the media type of a file from its extension
+    pub fn from_path<P: AsRef<Path>>(&self, path: P) -> Option<&ContentType> {
+        self.get(path.as_ref().extension()?.to_str()?)
+    }
+
+    /// Map a file extension to a media type, replacing any previous mapping
+    pub fn insert(&mut self, ext: &str, content_type: ContentType) -> &mut Self {
+        self.types.insert(ext.to_ascii_lowercase(), content_type);
+        self
+    }
+
+    /// Add the mappings of an Apache-style `mime.types` file,
+    /// returning how many extensions were mapped
+    ///
+    /// Every line holds a media type followed by its extensions, `#` starts a comment.
+    /// Lines with an invalid media type are skipped.
+    pub fn load<R: BufRead>(&mut self, reader: R) -> io::Result<usize> {
+        let mut mapped = 0;
+
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.split('#').next().unwrap_or("");
+
+            let mut fields = line.split_whitespace();
+            let content_type = match fields.next().map(ContentType::new) {
+                Some(Ok(content_type)) => content_type,
+                _ => continue,
+            };
+
+            for ext in fields {
+                self.insert(ext, content_type.clone());
+                mapped += 1;
+            }
+        }
+
+        Ok(mapped)
+    }
+
+    /// Add the mappings of the `mime.types` file at `path`, see load
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        self.load(BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Add mappings from a comma separated list such as `md=text/markdown,log=text/plain`
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidContentType if an entry is not `ext=media-type`,
+    /// entries before it have been added already
+    pub fn insert_list(&mut self, list: &str) -> Result<&mut Self, InvalidContentType> {
+        for entry in list.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let (ext, media_type) = match entry.find('=') {
+                Some(equals) => (entry[..equals].trim(), &entry[equals + 1..]),
+                None => return Err(InvalidContentType::from(entry)),
+            };
+            if ext.is_empty() {
+                return Err(InvalidContentType::from(entry));
+            }
+
+            self.insert(ext, ContentType::new(media_type)?);
+        }
+
+        Ok(self)
+    }
+}
+
+impl Default for MimeTypes {
+    fn default() -> Self {
+        let types = BUILTIN_TYPES
+            .iter()
+            .map(|(ext, media_type)| (ext.to_string(), ContentType(Cow::Borrowed(media_type))))
+            .collect();
+
+        MimeTypes { types }
+    }
+}
+
+fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(is_token_char)
+}
 
-    pub(super) fn as_str(&self) -> &str {
-        let content_types: HashMap<ContentType, &str> = [
-            (ContentType::CSS, "text/css"),
-            (ContentType::GIF, "image/gif"),
-            (ContentType::HTML, "text/html"),
-            (ContentType::HTML, "text/html"),
-            (ContentType::JPEG, "image/jpeg"),
-            (ContentType::JPEG, "image/jpeg"),
-            (ContentType::PNG, "image/png"),
-            (ContentType::SVG, "image/svg+xml"),
-            (ContentType::TEXT, "text/plain"),
-            (ContentType::XML, "application/xml"),
-            (ContentType::PDF, "application/pdf"),
-            (ContentType::ICO, "image/x-icon"),
-        ]
-        .iter()
-        .cloned()
-        .collect();
-        content_types.get(self).unwrap()
+/// Quote a parameter value unless it is a token
+fn quote(value: &str) -> Cow<'_, str> {
+    if is_token(value) {
+        Cow::Borrowed(value)
+    } else {
+        Cow::Owned(format!(
+            "\"{}\"",
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        ))
     }
 }
@@ -0,0 +1,164 @@
+//! HTTP-date formatting and parsing
+//!
+//! IMF-fixdate  = day-name "," SP date1 SP time-of-day SP GMT
+//!              ; fixed length/zone/capitalization subset of RFC 5322
+//!              e.g. Sun, 06 Nov 1994 08:49:37 GMT
+//!
+//! Recipients also accept the obsolete formats:
+//!
+//! rfc850-date  = day-name-l "," SP date2 SP time-of-day SP GMT
+//!              e.g. Sunday, 06-Nov-94 08:49:37 GMT
+//! asctime-date = day-name SP date3 SP time-of-day SP year
+//!              e.g. Sun Nov  6 08:49:37 1994
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Format a point in time as an IMF-fixdate, times before 1970 are clamped to the epoch
+///
+/// # Examples
+///
+/// ```
+/// use linda::date::http_date;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+/// assert_eq!(http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+pub fn http_date(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+
+    let days = seconds / SECONDS_PER_DAY;
+    let time_of_day = seconds % SECONDS_PER_DAY;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        // 1970-01-01 was a Thursday
+        DAY_NAMES[((days + 4) % 7) as usize],
+        day,
+        MONTH_NAMES[(month - 1) as usize],
+        year,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60
+    )
+}
+
+/// Parse an HTTP-date in any of the three formats, None if it is invalid, before 1970
+/// or too far in the future to be represented
+///
+/// # Examples
+///
+/// ```
+/// use linda::date::parse_http_date;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let time = Some(UNIX_EPOCH + Duration::from_secs(784_111_777));
+///
+/// assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), time);
+/// assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), time);
+/// assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), time);
+/// assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 CET"), None);
+/// assert_eq!(parse_http_date("Sun, 06 Nov 18446744073709551615 08:49:37 GMT"), None);
+/// ```
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let fields: Vec<&str> = date.split_whitespace().collect();
+
+    let (day, month, year, time_of_day) = match fields[..] {
+        [day_name, day, month, year, time_of_day, "GMT"] if day_name.ends_with(',') => {
+            (day, month, year.parse().ok()?, time_of_day)
+        }
+        [day_name, date, time_of_day, "GMT"] if day_name.ends_with(',') => {
+            let mut date = date.split('-');
+            let (day, month, year) = (date.next()?, date.next()?, date.next()?);
+            if date.next().is_some() || year.len() != 2 {
+                return None;
+            }
+
+            // Two digit years from the 1900s and 2000s
+            let year: u64 = year.parse().ok()?;
+            let year = if year < 70 { 2000 + year } else { 1900 + year };
+            (day, month, year, time_of_day)
+        }
+        [_, month, day, time_of_day, year] => (day, month, year.parse().ok()?, time_of_day),
+        _ => return None,
+    };
+
+    let day: u64 = parse_digits(day, 1, 2)?;
+    let month = MONTH_NAMES.iter().position(|name| *name == month)? as u64 + 1;
+    if year < 1970 || day == 0 || day > 31 {
+        return None;
+    }
+
+    let mut time_of_day = time_of_day.split(':');
+    let hours: u64 = parse_digits(time_of_day.next()?, 2, 2)?;
+    let minutes: u64 = parse_digits(time_of_day.next()?, 2, 2)?;
+    let seconds: u64 = parse_digits(time_of_day.next()?, 2, 2)?;
+    if time_of_day.next().is_some() || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    // Years far in the future overflow, whether in seconds or as a SystemTime
+    let seconds = days_from_civil(year, month, day)?
+        .checked_mul(SECONDS_PER_DAY)?
+        .checked_add(hours * 3600 + minutes * 60 + seconds)?;
+
+    UNIX_EPOCH.checked_add(Duration::from_secs(seconds))
+}
+
+/// Parse between `min` and `max` ASCII digits
+fn parse_digits(digits: &str, min: usize, max: usize) -> Option<u64> {
+    if digits.len() < min || digits.len() > max || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Convert a (year, month, day) proleptic Gregorian date from 1970 on to days since 1970-01-01
+///
+/// Inverse of civil_from_days, None if the number of days overflows,
+/// see <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    Some(era.checked_mul(146_097)?.checked_add(day_of_era)? - 719_468)
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) proleptic Gregorian date
+///
+/// Counts from 0000-03-01 so that leap days fall at the end of a year,
+/// see <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + (month <= 2) as u64;
+
+    (year, month, day)
+}
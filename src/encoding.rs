@@ -0,0 +1,175 @@
+//! Content codings and Accept-Encoding negotiation
+//!
+//! Accept-Encoding  = #( codings [ weight ] )
+//! codings          = content-coding / "identity" / "*"
+//! weight           = OWS ";" OWS "q=" qvalue
+
+use std::io::{self, Write};
+
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+
+/// Maximum body size in bytes compressed on the fly, larger bodies are streamed as is
+pub const MAX_COMPRESS_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Codings of precompressed files, in order of preference on equal quality
+pub const PRECOMPRESSED: [ContentCoding; 3] = [
+    ContentCoding::Brotli,
+    ContentCoding::Zstd,
+    ContentCoding::Gzip,
+];
+
+/// A content coding applied to a response body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCoding {
+    /// gzip file format (RFC 1952)
+    Gzip,
+    /// zlib format (RFC 1950) around a deflate stream
+    Deflate,
+    /// Brotli (RFC 7932), only served precompressed
+    Brotli,
+    /// Zstandard (RFC 8878), only served precompressed
+    Zstd,
+    /// No coding at all
+    Identity,
+}
+
+impl ContentCoding {
+    /// Get the coding name used in Accept-Encoding and Content-Encoding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+            ContentCoding::Brotli => "br",
+            ContentCoding::Zstd => "zstd",
+            ContentCoding::Identity => "identity",
+        }
+    }
+
+    /// Get the file extension of files precompressed with this coding, e.g. `.gz`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use linda::encoding::ContentCoding;
+    ///
+    /// assert_eq!(ContentCoding::Brotli.extension(), Some(".br"));
+    /// assert_eq!(ContentCoding::Identity.extension(), None);
+    /// ```
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            ContentCoding::Gzip => Some(".gz"),
+            ContentCoding::Brotli => Some(".br"),
+            ContentCoding::Zstd => Some(".zst"),
+            ContentCoding::Deflate | ContentCoding::Identity => None,
+        }
+    }
+
+    /// Whether `name` from an Accept-Encoding header stands for this coding
+    fn is_named(&self, name: &str) -> bool {
+        name.eq_ignore_ascii_case(self.as_str())
+            || (*self == ContentCoding::Gzip && name.eq_ignore_ascii_case("x-gzip"))
+    }
+
+    /// Encode `body` with this coding
+    ///
+    /// # Errors
+    ///
+    /// * Propagates errors of the compressor
+    /// * ErrorKind::Unsupported for Brotli and Zstd
+    pub fn encode(&self, body: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            ContentCoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            ContentCoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            ContentCoding::Brotli | ContentCoding::Zstd => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} can only be served precompressed", self.as_str()),
+            )),
+            ContentCoding::Identity => Ok(body.to_vec()),
+        }
+    }
+}
+
+/// Get the quality value the Accept-Encoding values give `coding`
+///
+/// Codings that are not listed take the quality of `*`, or 0 without it.
+/// Identity is acceptable unless it is excluded explicitly or through `*;q=0`.
+pub fn quality(accept_encoding: &[&str], coding: ContentCoding) -> f32 {
+    let mut any = None;
+
+    for element in accept_encoding.iter().flat_map(|value| value.split(',')) {
+        let mut parts = element.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        let quality = parts
+            .filter_map(|param| {
+                let (name, value) = param.split_at(param.find('=')?);
+                Some((name.trim(), value[1..].trim()))
+            })
+            .find(|(name, _)| name.eq_ignore_ascii_case("q"))
+            .and_then(|(_, value)| value.parse::<f32>().ok())
+            .map_or(1.0, |quality| quality.clamp(0.0, 1.0));
+
+        if coding.is_named(name) {
+            return quality;
+        } else if name == "*" {
+            any = Some(quality);
+        }
+    }
+
+    match (any, coding) {
+        (Some(quality), _) => quality,
+        (None, ContentCoding::Identity) => 1.0,
+        (None, _) => 0.0,
+    }
+}
+
+/// Choose the preferred coding among `supported`, in order of preference on equal quality
+///
+/// Identity is chosen when the client has no Accept-Encoding header,
+/// or prefers it over every supported coding.
+///
+/// # Examples
+///
+/// ```
+/// use linda::encoding::{negotiate, ContentCoding::*};
+///
+/// let supported = [Gzip, Deflate];
+///
+/// assert_eq!(negotiate(&["gzip, deflate, br"], &supported), Gzip);
+/// assert_eq!(negotiate(&["gzip;q=0.5, deflate"], &supported), Deflate);
+/// assert_eq!(negotiate(&["*;q=0.1, identity"], &supported), Identity);
+/// assert_eq!(negotiate(&["br"], &supported), Identity);
+/// assert_eq!(negotiate(&[], &supported), Identity);
+/// ```
+pub fn negotiate(accept_encoding: &[&str], supported: &[ContentCoding]) -> ContentCoding {
+    if accept_encoding.is_empty() {
+        return ContentCoding::Identity;
+    }
+
+    let identity = quality(accept_encoding, ContentCoding::Identity);
+    let mut best = (ContentCoding::Identity, 0.0);
+    for coding in supported {
+        let quality = quality(accept_encoding, *coding);
+        if quality > best.1 {
+            best = (*coding, quality);
+        }
+    }
+
+    if best.1 > 0.0 && best.1 >= identity {
+        best.0
+    } else {
+        ContentCoding::Identity
+    }
+}
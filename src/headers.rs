@@ -0,0 +1,232 @@
+//! HTTP header fields, shared by requests and responses
+
+use std::borrow::Cow;
+use std::{error, fmt};
+
+use crate::request::is_token_char;
+use crate::ContentType;
+
+/// Returned when a field could not be serialised without altering the message framing
+#[derive(Debug)]
+pub struct InvalidField(String);
+
+impl fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid header field: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidField {}
+
+/// Case-insensitive HTTP header multimap
+///
+/// Field order is preserved and a field-name may occur multiple times.
+/// Fields either borrow, e.g. from the received request head, or are owned.
+/// Responses are serialised in insertion order by Response::format_head.
+///
+/// Field-names have to be tokens and field-values must not contain CR, LF or NUL,
+/// which would allow injecting header fields or a body into a message.
+/// Use try_insert and try_append for fields that carry request data.
+///
+/// # Examples
+///
+/// ```
+/// use linda::headers::Headers;
+///
+/// let mut headers = Headers::new();
+/// headers
+///     .insert("Cache-Control", "no-cache")
+///     .append("Set-Cookie", "a=1")
+///     .append("set-cookie", "b=2")
+///     .content_length_mut(42);
+///
+/// assert_eq!(headers.get("cache-control"), Some("no-cache"));
+/// assert_eq!(headers.get_all("Set-Cookie"), vec!["a=1", "b=2"]);
+/// assert_eq!(headers.content_length(), Some(42));
+///
+/// headers.remove("Cache-Control");
+/// assert!(!headers.contains("Cache-Control"));
+///
+/// assert!(headers.try_append("Location", "/\r\nSet-Cookie: evil").is_err());
+/// assert_eq!(headers.len(), 3);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Headers<'a> {
+    fields: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+}
+
+impl<'a> Headers<'a> {
+    /// Create an empty header map
+    pub fn new() -> Self {
+        Headers::default()
+    }
+
+    /// Get the value of the first field named `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// Get the values of every field named `name`, in insertion order
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.iter()
+            .filter(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    /// Whether at least one field named `name` is present
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Set a field, replacing every existing field with the same name
+    ///
+    /// The field keeps the position of the first field it replaces.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field is invalid, see try_insert
+    pub fn insert<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<Cow<'a, str>>,
+        V: Into<Cow<'a, str>>,
+    {
+        match self.try_insert(name, value) {
+            Ok(headers) => headers,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Set a field like insert, unless it is invalid
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidField, leaving the headers unchanged, if the name is not
+    /// a token or the value contains CR, LF or NUL
+    pub fn try_insert<N, V>(&mut self, name: N, value: V) -> Result<&mut Self, InvalidField>
+    where
+        N: Into<Cow<'a, str>>,
+        V: Into<Cow<'a, str>>,
+    {
+        let (name, value) = validate(name.into(), value.into())?;
+
+        // Removing the fields with this name does not shift the position of the first one
+        let position = self.position(&name).unwrap_or(self.fields.len());
+        self.remove(&name);
+        self.fields.insert(position, (name, value));
+
+        Ok(self)
+    }
+
+    /// Add a field, keeping any existing fields with the same name
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field is invalid, see try_append
+    pub fn append<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<Cow<'a, str>>,
+        V: Into<Cow<'a, str>>,
+    {
+        match self.try_append(name, value) {
+            Ok(headers) => headers,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Add a field like append, unless it is invalid
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidField, leaving the headers unchanged, if the name is not
+    /// a token or the value contains CR, LF or NUL
+    pub fn try_append<N, V>(&mut self, name: N, value: V) -> Result<&mut Self, InvalidField>
+    where
+        N: Into<Cow<'a, str>>,
+        V: Into<Cow<'a, str>>,
+    {
+        let field = validate(name.into(), value.into())?;
+        self.fields.push(field);
+        Ok(self)
+    }
+
+    /// Remove every field named `name`, returning the value of the first one
+    pub fn remove(&mut self, name: &str) -> Option<Cow<'a, str>> {
+        let position = self.position(name)?;
+        let (_, value) = self.fields.remove(position);
+        self.fields
+            .retain(|(field, _)| !field.eq_ignore_ascii_case(name));
+
+        Some(value)
+    }
+
+    /// Iterate over all fields as (field-name, field-value) pairs
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value.as_ref()))
+    }
+
+    /// Number of fields, counting repeated field-names separately
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Whether there are no fields
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Get the Content-Type header
+    pub fn content_type(&self) -> Option<&str> {
+        self.get("Content-Type")
+    }
+
+    /// Set the Content-Type header
+    pub fn content_type_mut(&mut self, content_type: ContentType) -> &mut Self {
+        self.insert("Content-Type", content_type.as_str().to_string())
+    }
+
+    /// Get the Content-Length header, None if it is absent or not a number
+    pub fn content_length(&self) -> Option<u64> {
+        self.get("Content-Length")?.parse().ok()
+    }
+
+    /// Set the Content-Length header
+    ///
+    /// Only needed when it differs from the body length, e.g. for HEAD responses
+    pub fn content_length_mut(&mut self, content_length: u64) -> &mut Self {
+        self.insert("Content-Length", content_length.to_string())
+    }
+
+    /// Set the Connection header, announcing whether the connection stays open
+    pub fn connection_mut(&mut self, keep_alive: bool) -> &mut Self {
+        self.insert(
+            "Connection",
+            if keep_alive { "keep-alive" } else { "close" },
+        )
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.fields
+            .iter()
+            .position(|(field, _)| field.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Check that a field can be serialised without altering the message framing
+fn validate<'a>(
+    name: Cow<'a, str>,
+    value: Cow<'a, str>,
+) -> Result<(Cow<'a, str>, Cow<'a, str>), InvalidField> {
+    if name.is_empty() || !name.bytes().all(is_token_char) {
+        return Err(InvalidField(format!("name {:?}", name)));
+    }
+    if value.contains(['\r', '\n', '\0']) {
+        return Err(InvalidField(format!("value {:?}", value)));
+    }
+
+    Ok((name, value))
+}
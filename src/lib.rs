@@ -1,26 +1,60 @@
+pub mod autoindex;
+pub mod body;
+pub mod chunked;
+pub mod conditional;
+pub mod config;
 pub mod content;
+pub mod date;
+pub mod encoding;
+pub mod headers;
 pub mod method;
+pub mod range;
 pub mod request;
 pub mod response;
-mod status;
+#[cfg(target_os = "linux")]
+pub mod sendfile;
+pub mod status;
 pub mod threadpool;
+pub mod uri;
 
+use config::Config;
 use content::ContentType;
 use method::Method;
-use request::{get_request_line, parse_request_line, Request};
-use response::response;
+use request::{
+    get_request_line, is_timeout, parse_request, Parser, Request, RequestBody, RequestError,
+};
+use response::{response, Response};
 use status::StatusCode;
 
 use log::{error, info};
 use std::error::Error;
-use std::io::{Read, Write};
-use std::net::TcpStream;
+use std::io::{self, BufWriter, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::str;
+use std::time::{Duration, Instant};
+
+/// Longest time spent reading and discarding input before closing a connection
+const LINGER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Most bytes read and discarded before closing a connection
+const LINGER_MAX: u64 = 16 * 1024 * 1024;
+
+/// How often an idle connection checks whether it should give up its worker
+const IDLE_POLL: Duration = Duration::from_millis(100);
 
 /// Handles TcpStream connection
 ///
-/// Writes requests coming from the TcpStream to a buffer
+/// Reads from the TcpStream into a growing buffer until the request head is complete
+/// Parses the Request-Line and header fields into a Request
+/// Reads the message body announced by Content-Length or Transfer-Encoding
 /// Writes out the response for the corresponding request
 ///
+/// The connection is persistent: requests are served until the client asks for
+/// `Connection: close`, stays idle longer than the keep-alive timeout,
+/// or the maximum number of requests per connection is reached.
+/// Pipelined requests are answered strictly in the order they were received.
+/// Connections are closed gracefully, see linger.
+///
 /// # Errors
 ///
 /// Propagates errors up if:
@@ -28,32 +62,255 @@ use std::net::TcpStream;
 /// * The request was invlid
 /// * Was not able to write out response
 /// * Was not able to flush the stream
-#[allow(clippy::unused_io_amount)]
-pub fn handle_connection(mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
-    let mut buffer = [0u8; 1024];
+pub fn handle_connection(stream: TcpStream, config: &Config) -> Result<(), Box<dyn Error>> {
+    handle_connection_with(stream, config, &|| false)
+}
 
-    stream.read(&mut buffer)?;
+/// Handles TcpStream connection like handle_connection, closing it early
+/// while it is idle and `busy` returns true
+///
+/// Each connection keeps its worker while it waits for the next request.
+/// Pass whether other connections are waiting for a worker as `busy`,
+/// e.g. from a threadpool::Backlog, to give idle workers up to them.
+///
+/// # Errors
+///
+/// See handle_connection
+pub fn handle_connection_with(
+    stream: TcpStream,
+    config: &Config,
+    busy: &dyn Fn() -> bool,
+) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(config.keep_alive_timeout()))?;
+    stream.set_write_timeout(Some(config.write_timeout()))?;
 
-    let request_line = get_request_line(&buffer)?;
-    info!("Request-Line: {}", &request_line);
+    let mut parser = Parser::new(config.max_request_line(), config.max_head_size());
+    let mut writer = BufWriter::new(&stream);
+    // Response heads are serialised into the same buffer
+    let mut head = Vec::new();
 
-    // Get response from request_line
-    let response = match parse_request_line(&request_line) {
-        Ok(request) => response(&request),
-        Err(e) => {
-            error!("Bad request: {}", e);
-            Err(e)
+    // Whether the client may still be sending when the connection is closed
+    let mut pending = false;
+
+    for served in 1..=config.max_requests() {
+        // Nothing of the next request was received yet, only connections that
+        // were served before give their worker up
+        let idle = !parser.is_complete() && parser.leftover().is_empty();
+        let busy = if served > 1 { busy } else { &|| false };
+        if idle && !wait_for_request(&mut writer, config, busy)? {
+            break;
+        }
+
+        let (mut response, next) = match next_response(&mut parser, &mut writer, config) {
+            Ok(Some(served)) => served,
+            // Connection closed or idle for too long
+            Ok(None) => break,
+            Err(e) => {
+                error!("Response error: {}", e);
+                break;
+            }
+        };
+
+        let next = match next {
+            Next::Request(leftover) if served == config.max_requests() => Next::Close {
+                pending: !leftover.is_empty(),
+            },
+            next => next,
+        };
+        let headers = response.headers_mut();
+        if let (Some(server), false) = (config.server(), headers.contains("Server")) {
+            headers.insert("Server", server.to_string());
+        }
+        headers.connection_mut(matches!(next, Next::Request(_)));
+
+        info!("Response: {}", response);
+        if config.zero_copy() {
+            response.send_to(&mut writer, &mut head)?;
+        } else {
+            response.write_to(&mut writer, &mut head)?;
         }
-    };
 
-    match response {
-        Ok(mut response) => {
-            info!("Response: {}", response);
-            stream.write_all(&response.format_response())?;
-            stream.flush()?;
+        // Responses to pipelined requests that were already received are
+        // written out together, the writer is only flushed before reading
+        match next {
+            Next::Request(leftover) => parser.next_request(leftover),
+            Next::Close { pending: unread } => {
+                pending = unread;
+                break;
+            }
         }
-        Err(e) => error!("Response error: {}", e),
+    }
+
+    writer.flush()?;
+    if pending {
+        linger(&stream);
     }
 
     Ok(())
 }
+
+/// Wait for the first byte of a request, for at most the keep-alive timeout
+///
+/// Returns false if the connection was closed, stayed idle for too long,
+/// or `busy` returned true while waiting.
+fn wait_for_request(
+    writer: &mut BufWriter<&TcpStream>,
+    config: &Config,
+    busy: &dyn Fn() -> bool,
+) -> io::Result<bool> {
+    // The client may be waiting for the responses still buffered
+    writer.flush()?;
+    let stream = writer.get_ref();
+
+    let deadline = Instant::now() + config.keep_alive_timeout();
+    let received = loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if timeout.is_zero() || busy() {
+            break Ok(false);
+        }
+
+        stream.set_read_timeout(Some(timeout.min(IDLE_POLL)))?;
+        match stream.peek(&mut [0]) {
+            Ok(length) => break Ok(length > 0),
+            Err(e) if is_timeout(&e) || e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => break Err(e),
+        }
+    };
+
+    stream.set_read_timeout(Some(config.keep_alive_timeout()))?;
+    received
+}
+
+/// Shut down the sending side of `stream`, then read and discard what the client
+/// still sends until it closes the connection, for at most LINGER_TIMEOUT and LINGER_MAX bytes
+///
+/// Closing a socket with received bytes unread makes the kernel reset the connection,
+/// and the client may lose responses it has not read yet, e.g. a 413 sent while it is
+/// still uploading the body.
+fn linger(stream: &TcpStream) {
+    if stream.shutdown(Shutdown::Write).is_err() {
+        return;
+    }
+
+    let deadline = Instant::now() + LINGER_TIMEOUT;
+    let mut stream = stream.take(LINGER_MAX);
+    let mut buffer = [0; 8192];
+
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if timeout.is_zero() || stream.get_ref().set_read_timeout(Some(timeout)).is_err() {
+            return;
+        }
+
+        match stream.read(&mut buffer) {
+            Ok(0) => return,
+            Ok(_) => (),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(_) => return,
+        }
+    }
+}
+
+/// A Response, and how the connection continues after it
+type Served = (Response, Next);
+
+/// How the connection continues after a Response
+enum Next {
+    /// With the next request, starting with the bytes received past the end of this one
+    Request(Vec<u8>),
+    /// It is closed, `pending` if the client may still be sending input that was not read
+    Close { pending: bool },
+}
+
+/// Read the next request from the stream and build its response
+///
+/// Returns None if the stream was closed or timed out before a request arrived.
+/// Otherwise returns the response, and how the connection continues after it.
+fn next_response(
+    parser: &mut Parser,
+    writer: &mut BufWriter<&TcpStream>,
+    config: &Config,
+) -> Result<Option<Served>, Box<dyn Error>> {
+    let mut stream = FlushingReader(writer);
+
+    match parser.read_head(&mut stream) {
+        Ok(true) => (),
+        Ok(false) => return Ok(None),
+        Err(e) => return rejection(e),
+    }
+
+    let head = parser.head();
+    if let Ok(request_line) = get_request_line(head) {
+        info!("Request-Line: {}", &request_line);
+    }
+
+    let request = str::from_utf8(head)
+        .map_err(|e| RequestError::new(StatusCode::BAD_REQUEST, &e.to_string()))
+        .and_then(parse_request);
+    let mut request = match request {
+        Ok(request) => request,
+        Err(e) => return rejection(e.into()),
+    };
+
+    // The body is read from the stream as far as the response needs it
+    match RequestBody::new(&request, parser.leftover(), stream, config.max_body_size()) {
+        Ok(body) => request.body_stream_mut(body),
+        Err(e) => return rejection(e.into()),
+    };
+
+    let mut response = response(&request, config)?;
+    let head = *request.method() == Method::HEAD;
+    if config.compression() {
+        let accept_encoding = request.headers_all("Accept-Encoding");
+        let min_size = config.compression_min_size();
+        if head {
+            response.compress_head_mut(&accept_encoding, min_size);
+        } else {
+            response.compress_mut(&accept_encoding, min_size);
+        }
+    }
+    if head {
+        response.drop_body_mut();
+    }
+
+    // The rest of the body is skipped, the next request starts after it
+    match request.finish_body() {
+        Ok(leftover) if request.keep_alive() => Ok(Some((response, Next::Request(leftover)))),
+        Ok(leftover) => Ok(Some((
+            response,
+            Next::Close {
+                pending: !leftover.is_empty(),
+            },
+        ))),
+        Err(e) => rejection(e),
+    }
+}
+
+/// Turn a RequestError into a Response carrying its StatusCode, after which the
+/// connection is closed, other errors are passed on
+///
+/// The rest of the request may still be on its way, it is not read.
+fn rejection(e: Box<dyn Error>) -> Result<Option<Served>, Box<dyn Error>> {
+    error!("Rejected request: {}", e);
+
+    let e = e.downcast::<RequestError>()?;
+    let mut response = Response::new();
+    response.status_mut(e.status());
+
+    Ok(Some((response, Next::Close { pending: true })))
+}
+
+/// Reader over the socket behind a writer, flushing the writer before every read
+///
+/// Reads only reach the socket once the bytes already received are used up,
+/// and may then block until the client sends more. The client may itself be
+/// waiting for the responses still buffered, so those are sent first.
+struct FlushingReader<'w, 's>(&'w mut BufWriter<&'s TcpStream>);
+
+impl<'w, 's> Read for FlushingReader<'w, 's> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.flush()?;
+        let mut stream: &TcpStream = self.0.get_ref();
+        stream.read(buf)
+    }
+}
@@ -1,7 +1,9 @@
-use linda::handle_connection;
+use linda::config::Config;
+use linda::handle_connection_with;
 use linda::threadpool::ThreadPool;
 use log::{error, info};
 use std::net::TcpListener;
+use std::sync::Arc;
 
 fn main() {
     simple_logger::init().unwrap();
@@ -12,14 +14,24 @@ fn main() {
     let listener = TcpListener::bind(ip).expect("Unable to create listener.");
     info!("Server started on: {}{}", "http://", ip);
 
-    let pool = ThreadPool::new(16);
+    let config = Arc::new(Config::from_env());
+    let pool = ThreadPool::new(config.workers());
+    let backlog = pool.backlog();
 
     for stream in listener.incoming() {
         match stream {
-            Ok(stream) => pool.execute(|| match handle_connection(stream) {
-                Ok(_) => (),
-                Err(e) => error!("Error handling connection: {}", e),
-            }),
+            Ok(stream) => {
+                let config = Arc::clone(&config);
+                let backlog = backlog.clone();
+                // Idle connections are closed while others wait for a worker
+                let busy = move || !backlog.is_empty();
+                pool.execute(
+                    move || match handle_connection_with(stream, &config, &busy) {
+                        Ok(_) => (),
+                        Err(e) => error!("Error handling connection: {}", e),
+                    },
+                )
+            }
             Err(e) => error!("Connection failed: {}", e),
         }
     }
@@ -67,7 +67,7 @@ impl Method {
 
     /// Return HTTP method as Method object from a &str
     #[allow(clippy::should_implement_trait)]
-    pub fn from_str(src: &str) -> Result<Method, InvalidMethod> {
+    pub fn from_str(src: &str) -> Result<Method, InvalidMethod<'_>> {
         match src {
             "OPTIONS" => Ok(Method(Options)),
             "GET" => Ok(Method(Get)),
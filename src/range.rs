@@ -0,0 +1,251 @@
+//! Byte range requests
+//!
+//! Range           = byte-ranges-specifier
+//! byte-ranges-specifier = bytes-unit "=" byte-range-set
+//! byte-range-set  = 1#( byte-range-spec / suffix-byte-range-spec )
+//! byte-range-spec = first-byte-pos "-" [ last-byte-pos ]
+//! suffix-byte-range-spec = "-" suffix-length
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{error, fmt, io};
+
+use crate::body::Body;
+use crate::content::ContentType;
+use crate::date::parse_http_date;
+
+/// Maximum number of ranges accepted in one Range header, more are ignored
+pub const MAX_RANGES: usize = 64;
+
+/// Returned when a Range header can not be honoured
+#[derive(Debug, PartialEq)]
+pub enum RangeError {
+    /// The header is not a valid byte range set and has to be ignored
+    Invalid(String),
+    /// None of the ranges overlap the representation, answered with 416
+    Unsatisfiable,
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RangeError::Invalid(range) => write!(f, "Invalid range: {}", range),
+            RangeError::Unsatisfiable => write!(f, "Range not satisfiable"),
+        }
+    }
+}
+
+impl error::Error for RangeError {}
+
+/// An inclusive range of byte positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    start: u64,
+    end: u64,
+}
+
+impl ByteRange {
+    /// Get the first byte position
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Get the last byte position, inclusive
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Get the number of bytes in the range
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// A range always holds at least one byte
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Get the Content-Range value of this range within `length` bytes
+    pub fn content_range(&self, length: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, length)
+    }
+}
+
+/// Parse a Range header for a representation of `length` bytes
+///
+/// Unsatisfiable ranges are dropped, overlapping and adjacent ranges are merged.
+///
+/// # Errors
+///
+/// * RangeError::Invalid if the header is not a valid byte range set,
+///   uses another unit, or has more than MAX_RANGES ranges
+/// * RangeError::Unsatisfiable if no range overlaps the representation
+///
+/// # Examples
+///
+/// ```
+/// use linda::range::{parse_range, RangeError};
+///
+/// let ranges = parse_range("bytes=0-499, -100, 9500-", 10_000).unwrap();
+/// let ranges: Vec<_> = ranges.iter().map(|range| (range.start(), range.end())).collect();
+/// assert_eq!(ranges, [(0, 499), (9500, 9999)]);
+///
+/// assert_eq!(parse_range("bytes=20000-", 10_000), Err(RangeError::Unsatisfiable));
+/// assert!(parse_range("lines=1-2", 10_000).is_err());
+/// assert!(parse_range("bytesé", 10_000).is_err());
+/// ```
+pub fn parse_range(header: &str, length: u64) -> Result<Vec<ByteRange>, RangeError> {
+    let invalid = || RangeError::Invalid(header.to_string());
+
+    let header = header.trim();
+    if !header
+        .get(..6)
+        .is_some_and(|unit| unit.eq_ignore_ascii_case("bytes="))
+    {
+        return Err(invalid());
+    }
+
+    let specs: Vec<&str> = header[6..]
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+        .collect();
+    if specs.is_empty() || specs.len() > MAX_RANGES {
+        return Err(invalid());
+    }
+
+    let mut ranges = Vec::new();
+    for spec in specs {
+        let dash = spec.find('-').ok_or_else(invalid)?;
+        let (first, last) = (spec[..dash].trim(), spec[dash + 1..].trim());
+
+        let range = match (parse_position(first), parse_position(last)) {
+            // suffix-byte-range-spec
+            (None, Some(suffix)) if first.is_empty() => Some(suffix)
+                .filter(|&suffix| suffix > 0 && length > 0)
+                .map(|suffix| ByteRange {
+                    start: length.saturating_sub(suffix),
+                    end: length - 1,
+                }),
+            (Some(start), None) if last.is_empty() => Some(start)
+                .filter(|&start| start < length)
+                .map(|start| ByteRange {
+                    start,
+                    end: length - 1,
+                }),
+            (Some(start), Some(end)) if start <= end => Some(start)
+                .filter(|&start| start < length)
+                .map(|start| ByteRange {
+                    start,
+                    end: end.min(length - 1),
+                }),
+            _ => return Err(invalid()),
+        };
+        ranges.extend(range);
+    }
+
+    if ranges.is_empty() {
+        return Err(RangeError::Unsatisfiable);
+    }
+
+    Ok(merge(ranges))
+}
+
+/// Parse a byte position, None if it is empty or not made of digits
+///
+/// Positions beyond u64 saturate, they are past the end of any file anyway.
+fn parse_position(position: &str) -> Option<u64> {
+    if position.is_empty() || !position.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(position.parse().unwrap_or(u64::MAX))
+}
+
+/// Sort ranges and merge the ones that overlap or touch
+fn merge(mut ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(1) => {
+                last.end = last.end.max(range.end);
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+/// Whether an If-Range validator still matches the representation,
+/// so that the Range header is honoured
+///
+/// `etag` is the current entity tag, if any. Entity tags must match strongly,
+/// dates must equal the modification time exactly.
+pub fn if_range_matches(if_range: &str, etag: Option<&str>, modified: Option<SystemTime>) -> bool {
+    let if_range = if_range.trim();
+
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        return match etag {
+            Some(etag) => !etag.starts_with("W/") && if_range == etag,
+            None => false,
+        };
+    }
+
+    match (parse_http_date(if_range), modified) {
+        (Some(date), Some(modified)) => {
+            let seconds =
+                |time: SystemTime| time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
+            seconds(date).is_some() && seconds(date) == seconds(modified)
+        }
+        _ => false,
+    }
+}
+
+/// Create a boundary for a multipart/byteranges body, unique within this process
+pub fn boundary() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.subsec_nanos());
+    format!(
+        "linda-{:08x}{:08x}",
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Build a multipart/byteranges body holding `ranges` of `body`
+///
+/// Every part carries the Content-Type of the whole representation and its Content-Range.
+/// The ranges are sliced out of `body` and only read when the multipart body is written.
+///
+/// # Errors
+///
+/// Propagates errors of Body::slice
+pub fn multipart_byteranges(
+    body: &Body,
+    ranges: &[ByteRange],
+    content_type: &ContentType,
+    boundary: &str,
+) -> io::Result<Body> {
+    let length = body.len();
+    let mut parts = Vec::with_capacity(ranges.len() * 2 + 1);
+
+    for (i, range) in ranges.iter().enumerate() {
+        // The CRLF ending a part's data belongs to the next delimiter
+        parts.push(Body::from(format!(
+            "{}--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+            if i == 0 { "" } else { "\r\n" },
+            boundary,
+            content_type,
+            range.content_range(length)
+        )));
+        parts.push(body.slice(range.start, range.len())?);
+    }
+    parts.push(Body::from(format!("\r\n--{}--\r\n", boundary)));
+
+    Ok(Body::Parts(parts))
+}
@@ -1,38 +1,77 @@
 //! HTTP request type
 
+use std::borrow::Cow;
 use std::error::Error;
+use std::io::{self, BufReader, Read};
 use std::path::Path;
-use std::{error, fmt, str};
+use std::{error, fmt, mem, str};
 
+use crate::chunked::ChunkedReader;
+pub use crate::headers::Headers;
+use crate::uri::{decode_path, parse_query, split_target, InvalidUri};
 use crate::Method;
+use crate::StatusCode;
 
 #[derive(Debug)]
-struct InvalidUri<'a>(&'a str);
+struct RequestLineNotFound;
+
+impl fmt::Display for RequestLineNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Request-Line not found")
+    }
+}
+
+impl error::Error for RequestLineNotFound {}
+
+/// Returned when a header line is not of the form `field-name ":" field-value`
+#[derive(Debug)]
+pub struct InvalidHeader<'a>(&'a str);
 
-impl<'a> fmt::Display for InvalidUri<'a> {
+impl<'a> fmt::Display for InvalidHeader<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Invalid URI: {}", self.0)
+        write!(f, "Invalid header field: {}", self.0)
     }
 }
 
-impl<'a> From<&'a str> for InvalidUri<'a> {
+impl<'a> From<&'a str> for InvalidHeader<'a> {
     fn from(error: &'a str) -> Self {
-        InvalidUri(error)
+        InvalidHeader(error)
     }
 }
 
-impl<'a> error::Error for InvalidUri<'a> {}
+impl<'a> error::Error for InvalidHeader<'a> {}
 
+/// Returned when a request can be answered, but not served
+///
+/// Carries the StatusCode the response should have, e.g. 413 for a body
+/// exceeding the configured maximum
 #[derive(Debug)]
-struct RequestLineNotFound;
+pub struct RequestError {
+    status: StatusCode,
+    reason: String,
+}
 
-impl fmt::Display for RequestLineNotFound {
+impl RequestError {
+    pub(super) fn new(status: StatusCode, reason: &str) -> Self {
+        RequestError {
+            status,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// StatusCode to respond with
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl fmt::Display for RequestError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Request-Line not found")
+        write!(f, "{} {}", self.status, self.reason)
     }
 }
 
-impl error::Error for RequestLineNotFound {}
+impl error::Error for RequestError {}
 
 /// Return a Request-Line given a buffer
 ///
@@ -42,7 +81,7 @@ impl error::Error for RequestLineNotFound {}
 /// * buffer is not UTF-8
 /// * Request-Line was not found (RequestLineNotFound Error type)
 pub fn get_request_line(buffer: &[u8]) -> Result<&str, Box<dyn Error>> {
-    let request = str::from_utf8(&buffer[..])?;
+    let request = str::from_utf8(buffer)?;
     Ok(request.lines().next().ok_or(RequestLineNotFound)?)
 }
 
@@ -51,36 +90,625 @@ pub fn get_request_line(buffer: &[u8]) -> Result<&str, Box<dyn Error>> {
 ///
 /// # Errors
 ///
-/// Propagates errors up if:
+/// Returns a RequestError with StatusCode 400 if:
 /// * Not specified:
 ///     - Request Method
 ///     - Request URI
 ///     - Request HTTP Version
-/// * If Method invalid (InvalidMethod Error type), see Method
-/// * If the HTTP version is not supported
-pub fn parse_request_line<'a>(request: &'a str) -> Result<Request, Box<dyn Error + 'a>> {
+/// * If Method invalid, see Method
+///
+/// or with StatusCode 505 if the HTTP version is not supported, see Request::version_mut
+pub fn parse_request_line(request: &str) -> Result<Request<'_>, RequestError> {
     let mut parts = request.split_whitespace();
 
-    let method = parts.next().ok_or("Method not specified")?;
-    let uri = parts.next().ok_or("URI not specified")?;
-    let http_version = parts.next().ok_or("HTTP version not specified")?;
+    let method = parts
+        .next()
+        .ok_or_else(|| bad_request("Method not specified"))?;
+    let uri = parts
+        .next()
+        .ok_or_else(|| bad_request("URI not specified"))?;
+    let http_version = parts
+        .next()
+        .ok_or_else(|| bad_request("HTTP version not specified"))?;
 
     let mut request = Request::new();
     request
-        .method_mut(method)?
+        .method_mut(method)
+        .map_err(|e| bad_request(&e.to_string()))?
         .uri_mut(uri)
         .version_mut(http_version)?;
 
     Ok(request)
 }
 
+/// Takes in a request head (Request-Line followed by the header fields)
+/// and returns a Request with its headers parsed
+///
+/// Parsing stops at the first empty line, which terminates the head.
+///
+/// # Errors
+///
+/// Returns a RequestError with StatusCode 400 if:
+/// * Request-Line was not found
+/// * The Request-Line is invalid, see parse_request_line (505 for HTTP versions)
+/// * A header line is malformed, see parse_header
+pub fn parse_request(request: &str) -> Result<Request<'_>, RequestError> {
+    let mut lines = request.lines();
+
+    let request_line = lines
+        .next()
+        .ok_or_else(|| bad_request(&RequestLineNotFound.to_string()))?;
+    let mut request = parse_request_line(request_line)?;
+
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let (name, value) = parse_header(line).map_err(|e| bad_request(&e.to_string()))?;
+        request
+            .headers
+            .try_append(name, value)
+            .map_err(|e| bad_request(&e.to_string()))?;
+    }
+
+    Ok(request)
+}
+
+/// Create a RequestError answered with 400 Bad Request
+fn bad_request(reason: &str) -> RequestError {
+    RequestError::new(StatusCode::BAD_REQUEST, reason)
+}
+
+/// Split a single header line into its field-name and field-value
+///
+/// message-header = field-name ":" OWS field-value OWS
+///
+/// # Errors
+///
+/// Returns InvalidHeader if:
+/// * There is no ':' separator
+/// * The field-name is empty or not a token (e.g. contains whitespace)
+/// * The line is an obsolete folded continuation line
+pub fn parse_header(line: &str) -> Result<(&str, &str), InvalidHeader<'_>> {
+    let colon = line.find(':').ok_or(InvalidHeader(line))?;
+    let (name, value) = (&line[..colon], &line[colon + 1..]);
+
+    if name.is_empty() || !name.bytes().all(is_token_char) {
+        return Err(InvalidHeader(line));
+    }
+
+    Ok((name, value.trim_matches([' ', '\t'])))
+}
+
+/// Number of bytes requested from the stream per read while accumulating a head
+const READ_SIZE: usize = 4096;
+
+/// Incremental request head parser
+///
+/// Accumulates bytes over as many reads as needed until the empty line
+/// terminating the request head, enforcing limits on the Request-Line length
+/// and on the total head size. Bytes received after the head are kept
+/// as leftover, they belong to the body or to the next pipelined request.
+///
+/// # Examples
+///
+/// ```
+/// use linda::request::{parse_request, Parser};
+/// # use std::error::Error;
+///
+/// let mut parser = Parser::new(8192, 16384);
+///
+/// assert!(!parser.feed(b"GET / HTTP/1.1\r\nHo")?);
+/// assert!(parser.feed(b"st: localhost\r\n\r\nGET")?);
+///
+/// let head = std::str::from_utf8(parser.head())?;
+/// let request = parse_request(head)?;
+/// assert_eq!(request.header("Host"), Some("localhost"));
+/// assert_eq!(parser.leftover(), b"GET");
+///
+/// # Ok::<(), Box<dyn Error>>(())
+/// ```
+pub struct Parser {
+    buffer: Vec<u8>,
+    head_end: Option<usize>,
+    scanned: usize,
+    max_request_line: usize,
+    max_head_size: usize,
+}
+
+impl Parser {
+    /// Create a parser accepting a Request-Line of at most `max_request_line` bytes
+    /// and a head of at most `max_head_size` bytes, both including line terminators
+    pub fn new(max_request_line: usize, max_head_size: usize) -> Self {
+        Parser {
+            buffer: Vec::new(),
+            head_end: None,
+            scanned: 0,
+            max_request_line,
+            max_head_size,
+        }
+    }
+
+    /// Append received bytes, returning whether the head is complete
+    ///
+    /// Once the head is complete, further bytes are only added to the leftover.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RequestError if:
+    /// * The Request-Line exceeds the limit (414)
+    /// * The head exceeds the limit (431)
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<bool, RequestError> {
+        self.buffer.extend_from_slice(bytes);
+
+        if self.head_end.is_none() {
+            self.find_head_end();
+            self.check_limits()?;
+        }
+
+        Ok(self.head_end.is_some())
+    }
+
+    /// Read from `stream` until the head is complete
+    ///
+    /// Returns false if the stream was closed or its read timeout expired
+    /// before any byte of a new request arrived.
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors up if:
+    /// * Reading from the stream failed
+    /// * The stream was closed in the middle of the head
+    /// * The read timeout expired in the middle of the head (408 RequestError)
+    /// * A limit was exceeded, see feed
+    pub fn read_head<R: Read>(&mut self, mut stream: R) -> Result<bool, Box<dyn Error>> {
+        let mut buffer = [0u8; READ_SIZE];
+
+        while !self.feed(&[])? {
+            let length = match stream.read(&mut buffer) {
+                Ok(length) => length,
+                // Idle persistent connection timed out
+                Err(e) if is_timeout(&e) && self.buffer.is_empty() => return Ok(false),
+                Err(e) if is_timeout(&e) => {
+                    return Err(RequestError::new(
+                        StatusCode::REQUEST_TIME_OUT,
+                        "Incomplete request head",
+                    )
+                    .into());
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if length == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(false);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Incomplete request head",
+                )
+                .into());
+            }
+
+            self.feed(&buffer[..length])?;
+        }
+
+        Ok(true)
+    }
+
+    /// Get the request head including the terminating empty line, empty until complete
+    pub fn head(&self) -> &[u8] {
+        &self.buffer[..self.head_end.unwrap_or(0)]
+    }
+
+    /// Get the bytes received after the head
+    pub fn leftover(&self) -> &[u8] {
+        &self.buffer[self.head_end.unwrap_or(0)..]
+    }
+
+    /// Whether a complete head has been received
+    pub fn is_complete(&self) -> bool {
+        self.head_end.is_some()
+    }
+
+    /// Start parsing the next request from `leftover`, the bytes received past
+    /// the end of the previous request's body
+    ///
+    /// Pipelined requests may already be complete, see is_complete.
+    /// Limits are enforced by the next feed or read_head.
+    pub fn next_request(&mut self, leftover: Vec<u8>) {
+        self.buffer = leftover;
+        self.head_end = None;
+        self.scanned = 0;
+        self.find_head_end();
+    }
+
+    /// Look for the empty line terminating the head, starting where the last search ended
+    fn find_head_end(&mut self) {
+        // Empty lines before the Request-Line are ignored, see RFC 7230 section 3.5
+        let mut blank = 0;
+        loop {
+            match &self.buffer[blank..] {
+                [b'\r', b'\n', ..] => blank += 2,
+                [b'\n', ..] => blank += 1,
+                _ => break,
+            }
+        }
+        if blank > 0 {
+            self.buffer.drain(..blank);
+            self.scanned = self.scanned.saturating_sub(blank);
+        }
+
+        while let Some(offset) = self.buffer[self.scanned..].iter().position(|&c| c == b'\n') {
+            let newline = self.scanned + offset;
+
+            match &self.buffer[newline + 1..] {
+                [b'\n', ..] => self.head_end = Some(newline + 2),
+                [b'\r', b'\n', ..] => self.head_end = Some(newline + 3),
+                // Not enough bytes yet to tell if the next line is empty
+                [] | [b'\r'] => return,
+                _ => (),
+            }
+            if self.head_end.is_some() {
+                return;
+            }
+
+            self.scanned = newline + 1;
+        }
+
+        self.scanned = self.buffer.len();
+    }
+
+    fn check_limits(&self) -> Result<(), RequestError> {
+        let head = match self.head_end {
+            Some(head_end) => &self.buffer[..head_end],
+            None => &self.buffer[..],
+        };
+
+        let request_line = head
+            .iter()
+            .position(|&c| c == b'\n')
+            .map_or(head.len(), |i| i + 1);
+        if request_line > self.max_request_line {
+            return Err(RequestError::new(
+                StatusCode::REQUEST_URI_TOO_LARGE,
+                "Request-Line too long",
+            ));
+        }
+
+        if head.len() > self.max_head_size {
+            return Err(RequestError::new(
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+                "Request head too large",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a read failed because the socket read timeout expired
+pub(crate) fn is_timeout(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut
+}
+
+/// Read the message body of a request whose head has already been parsed
+///
+/// `received` holds the bytes that were read past the end of the head,
+/// the rest of the body is read from `stream`.
+/// The body is held in memory and chunked trailer fields are stored on the request,
+/// see RequestBody to stream the body instead.
+///
+/// Returns the bytes that were received past the end of the body,
+/// they belong to the next request on the connection.
+///
+/// # Errors
+///
+/// Propagates errors up if:
+/// * The body can not be framed, see RequestBody::new
+/// * The body can not be read, see RequestBody::finish
+pub fn read_body<'b, R: Read + 'b>(
+    request: &mut Request,
+    received: &'b [u8],
+    stream: R,
+    max_body_size: usize,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut reader = RequestBody::new(request, received, stream, max_body_size)?;
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body).map_err(body_error)?;
+
+    let (leftover, trailers) = reader.finish()?;
+    request.body_mut(body);
+    request.trailers = trailers;
+
+    Ok(leftover)
+}
+
+/// Streaming reader over a request body, reading it from the stream as it is consumed
+///
+/// The body is framed by Content-Length or decoded from the chunked transfer-coding.
+/// Reading fails with a 413 RequestError as soon as a chunked body exceeds the maximum,
+/// and with UnexpectedEof if the stream ends before the body does.
+///
+/// # Examples
+///
+/// ```
+/// use linda::request::{parse_request, RequestBody};
+/// use std::io::{self, Read};
+/// # use std::error::Error;
+///
+/// let request = parse_request("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n")?;
+/// let received = b"4\r\nWiki\r\n5\r\npedia\r\n0\r\nExpires: never\r\n\r\nGET / HTTP/1.1";
+/// let mut body = RequestBody::new(&request, received, io::empty(), 1024)?;
+///
+/// let mut start = [0; 4];
+/// body.read_exact(&mut start)?;
+/// assert_eq!(&start, b"Wiki");
+///
+/// let (leftover, trailers) = body.finish()?;
+/// assert_eq!(leftover, b"GET / HTTP/1.1");
+/// assert_eq!(trailers.get("Expires"), Some("never"));
+///
+/// # Ok::<(), Box<dyn Error>>(())
+/// ```
+pub struct RequestBody<'b> {
+    framing: Framing<'b>,
+    max_body_size: usize,
+    read: usize,
+}
+
+/// Stream a body is read from once the received bytes are used up
+type Stream<'b> = Box<dyn Read + 'b>;
+
+/// How the end of a request body is found
+enum Framing<'b> {
+    /// Content-Length, with the bytes received past the end of the head
+    Length {
+        reader: BodyReader<'b, Stream<'b>>,
+        received: &'b [u8],
+        length: usize,
+    },
+    Chunked(ChunkedReader<BufReader<io::Chain<&'b [u8], Stream<'b>>>>),
+}
+
+impl<'b> RequestBody<'b> {
+    /// Frame the body of `request`, nothing is read until the body is
+    ///
+    /// `received` holds the bytes that were read past the end of the head,
+    /// the rest of the body is read from `stream`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RequestError if:
+    /// * Content-Length is invalid (400)
+    /// * Both Content-Length and Transfer-Encoding are present (400)
+    /// * A transfer-coding other than chunked is used (501)
+    /// * POST or PUT request has neither (411)
+    /// * Content-Length exceeds `max_body_size` (413)
+    pub fn new<R: Read + 'b>(
+        request: &Request,
+        received: &'b [u8],
+        stream: R,
+        max_body_size: usize,
+    ) -> Result<Self, RequestError> {
+        let stream: Stream<'b> = Box::new(stream);
+
+        let framing = if request.headers.contains("Transfer-Encoding") {
+            check_chunked(request)?;
+            Framing::Chunked(ChunkedReader::new(BufReader::new(received.chain(stream))))
+        } else {
+            let length = match request.content_length()? {
+                Some(length) => length,
+                None if *request.method() == Method::POST || *request.method() == Method::PUT => {
+                    return Err(RequestError::new(
+                        StatusCode::LENGTH_REQUIRED,
+                        "Content-Length missing",
+                    ));
+                }
+                None => 0,
+            };
+
+            if length > max_body_size {
+                return Err(RequestError::new(
+                    StatusCode::REQUEST_ENTITY_TOO_LARGE,
+                    "Body too large",
+                ));
+            }
+
+            Framing::Length {
+                reader: BodyReader::new(received, stream, length),
+                received,
+                length,
+            }
+        };
+
+        Ok(RequestBody {
+            framing,
+            max_body_size,
+            read: 0,
+        })
+    }
+
+    /// Read and discard the rest of the body
+    ///
+    /// Returns the bytes that were received past the end of the body, they belong
+    /// to the next request on the connection, and the trailer fields of a chunked body.
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors up if:
+    /// * The chunked body is malformed (400 RequestError)
+    /// * The chunked body exceeds `max_body_size` (413 RequestError)
+    /// * The stream ends before the whole body was received
+    pub fn finish(mut self) -> Result<(Vec<u8>, Headers<'static>), Box<dyn Error>> {
+        io::copy(&mut self, &mut io::sink()).map_err(body_error)?;
+
+        match self.framing {
+            Framing::Length {
+                received, length, ..
+            } => Ok((
+                received[length.min(received.len())..].to_vec(),
+                Headers::new(),
+            )),
+            Framing::Chunked(reader) => {
+                let (reader, trailers) = reader.into_parts();
+
+                // Bytes buffered past the end of the body, followed by received bytes not read yet
+                let mut leftover = reader.buffer().to_vec();
+                let (received, _) = reader.into_inner().into_inner();
+                leftover.extend_from_slice(received);
+
+                Ok((leftover, trailers))
+            }
+        }
+    }
+}
+
+impl<'b> Read for RequestBody<'b> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = match &mut self.framing {
+            Framing::Length { reader, .. } => {
+                let read = reader.read(buf)?;
+                if read == 0 && !buf.is_empty() && reader.remaining > 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Body shorter than Content-Length",
+                    ));
+                }
+                read
+            }
+            Framing::Chunked(reader) => reader.read(buf)?,
+        };
+
+        self.read += read;
+        if self.read > self.max_body_size {
+            return Err(io::Error::other(RequestError::new(
+                StatusCode::REQUEST_ENTITY_TOO_LARGE,
+                "Body too large",
+            )));
+        }
+
+        Ok(read)
+    }
+}
+
+/// An empty body, as sent with a GET request
+impl<'b> Default for RequestBody<'b> {
+    fn default() -> Self {
+        RequestBody {
+            framing: Framing::Length {
+                reader: BodyReader::new(&[], Box::new(io::empty()), 0),
+                received: &[],
+                length: 0,
+            },
+            max_body_size: 0,
+            read: 0,
+        }
+    }
+}
+
+/// Check that a request with Transfer-Encoding can be decoded as chunked
+fn check_chunked(request: &Request) -> Result<(), RequestError> {
+    // A message with both is a request smuggling vector, see RFC 7230 section 3.3.3
+    if request.headers.contains("Content-Length") {
+        return Err(RequestError::new(
+            StatusCode::BAD_REQUEST,
+            "Both Content-Length and Transfer-Encoding",
+        ));
+    }
+
+    let codings = request.headers_all("Transfer-Encoding").join(",");
+    let mut codings = codings.split(',').map(str::trim).filter(|c| !c.is_empty());
+    if !codings
+        .next_back()
+        .is_some_and(|c| c.eq_ignore_ascii_case("chunked"))
+        || codings.next().is_some()
+    {
+        return Err(RequestError::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "Unsupported Transfer-Encoding",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Recover the RequestError of a failed body read, malformed chunked bodies are 400
+fn body_error(e: io::Error) -> Box<dyn Error> {
+    if e.get_ref().is_some_and(|inner| inner.is::<RequestError>()) {
+        return e.into_inner().unwrap();
+    }
+
+    match e.kind() {
+        io::ErrorKind::InvalidData => {
+            RequestError::new(StatusCode::BAD_REQUEST, &e.to_string()).into()
+        }
+        _ => e.into(),
+    }
+}
+
+/// Streaming reader over a message body of known length
+///
+/// Yields the body bytes that were already received together with the head,
+/// then reads the remainder from the underlying stream and stops at the end of the body.
+pub struct BodyReader<'b, R> {
+    received: &'b [u8],
+    stream: R,
+    remaining: usize,
+}
+
+impl<'b, R: Read> BodyReader<'b, R> {
+    /// Create a reader for a body of `length` bytes
+    pub fn new(received: &'b [u8], stream: R, length: usize) -> Self {
+        let received = &received[..received.len().min(length)];
+        BodyReader {
+            remaining: length - received.len(),
+            received,
+            stream,
+        }
+    }
+}
+
+impl<'b, R: Read> Read for BodyReader<'b, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.received.is_empty() {
+            return self.received.read(buf);
+        }
+
+        // A zero-length read would block on a socket until more data arrives
+        let max = buf.len().min(self.remaining);
+        if max == 0 {
+            return Ok(0);
+        }
+
+        let read = self.stream.read(&mut buf[..max])?;
+        self.remaining -= read;
+
+        Ok(read)
+    }
+}
+
+/// tchar as defined in RFC 7230 section 3.2.6
+pub(crate) fn is_token_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c)
+}
+
 /// HTTP Request representation
 ///
+/// Request       = Request-Line
+///                 *(( general-header
+///                 | request-header
+///                 | entity-header ) CRLF)
+///                 CRLF
 /// Request-Line = Method SP Request-URI SP HTTP-Version CRLF
 pub struct Request<'a> {
     method: Method,
     uri: &'a Path,
+    path: &'a str,
+    query: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    query_string: Option<&'a str>,
     version: &'a str,
+    headers: Headers<'a>,
+    body: Vec<u8>,
+    body_stream: RequestBody<'a>,
+    trailers: Headers<'static>,
 }
 
 /// Builder patterns for Request
@@ -94,8 +722,17 @@ pub struct Request<'a> {
 /// let mut request = Request::new();
 /// request
 /// .method_mut("GET")?
-/// .uri_mut("/")
-/// .version_mut("HTTP/1.1")?;
+/// .uri_mut("/search?tag=a&tag=b")
+/// .version_mut("HTTP/1.1")?
+/// .header_mut("Host", "localhost")
+/// .header_mut("Accept", "text/html")
+/// .header_mut("accept", "*/*");
+///
+/// assert_eq!(request.header("host"), Some("localhost"));
+/// assert_eq!(request.headers_all("ACCEPT"), vec!["text/html", "*/*"]);
+/// assert_eq!(request.path()?, "/search");
+/// assert_eq!(request.query("tag"), Some("a"));
+/// assert_eq!(request.query_all("tag"), vec!["a", "b"]);
 ///
 /// # Ok::<(), Box<Error>>(())
 /// ```
@@ -105,14 +742,129 @@ impl<'a> Request<'a> {
         &self.method
     }
 
-    /// Get Request uri
+    /// Get Request uri, the request-target as sent by the client
     pub fn uri(&self) -> &Path {
-        &self.uri
+        self.uri
+    }
+
+    /// Get the percent-decoded path of the Request uri, without query or fragment
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidUri if the path can not be decoded safely, see decode_path
+    pub fn path(&self) -> Result<Cow<'a, str>, InvalidUri> {
+        decode_path(self.path)
+    }
+
+    /// Get the query of the Request uri as sent, None if there is no `?`
+    pub fn query_string(&self) -> Option<&str> {
+        self.query_string
+    }
+
+    /// Get the first decoded value of a query parameter
+    pub fn query(&self, key: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_ref())
+    }
+
+    /// Get every decoded value of a query parameter, in request order
+    pub fn query_all(&self, key: &str) -> Vec<&str> {
+        self.query
+            .iter()
+            .filter(|(name, _)| name == key)
+            .map(|(_, value)| value.as_ref())
+            .collect()
     }
 
     /// Get Request uri
     pub fn version(&self) -> &str {
-        &self.version
+        self.version
+    }
+
+    /// Get Request headers
+    pub fn headers(&self) -> &Headers<'a> {
+        &self.headers
+    }
+
+    /// Get the first value of a Request header, the name is case-insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// Get every value of a Request header, the name is case-insensitive
+    pub fn headers_all(&self, name: &str) -> Vec<&str> {
+        self.headers.get_all(name)
+    }
+
+    /// Whether the client wants the connection kept open after this request
+    ///
+    /// HTTP/1.1 connections are persistent unless the Connection header has a `close` option
+    pub fn keep_alive(&self) -> bool {
+        !self
+            .headers_all("Connection")
+            .iter()
+            .flat_map(|value| value.split(','))
+            .any(|option| option.trim().eq_ignore_ascii_case("close"))
+    }
+
+    /// Get the trailer fields sent after a chunked body,
+    /// once it was read with read_body or finish_body
+    pub fn trailers(&self) -> &Headers<'static> {
+        &self.trailers
+    }
+
+    /// Get the Content-Length header as a number, None if it is absent
+    ///
+    /// # Errors
+    ///
+    /// Returns a 400 RequestError if the value is not a number,
+    /// or if Content-Length is repeated, even with equal values
+    pub fn content_length(&self) -> Result<Option<usize>, RequestError> {
+        match self.headers_all("Content-Length")[..] {
+            [] => Ok(None),
+            [value] => match value.parse() {
+                Ok(length) if value.bytes().all(|c| c.is_ascii_digit()) => Ok(Some(length)),
+                _ => Err(RequestError::new(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid Content-Length",
+                )),
+            },
+            // Recipients may disagree on which one counts, a request smuggling vector
+            _ => Err(RequestError::new(
+                StatusCode::BAD_REQUEST,
+                "Repeated Content-Length",
+            )),
+        }
+    }
+
+    /// Get the Request body held in memory, empty if the request had none
+    ///
+    /// read_body reads the whole body into memory, a body attached with
+    /// body_stream_mut is only read through body_reader.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Get a reader over the Request body
+    ///
+    /// Yields the body held in memory, followed by the body attached with
+    /// body_stream_mut, which is read from the stream as it is consumed.
+    pub fn body_reader(&mut self) -> io::Chain<&[u8], &mut RequestBody<'a>> {
+        (&self.body[..]).chain(&mut self.body_stream)
+    }
+
+    /// Read and discard the rest of the body attached with body_stream_mut,
+    /// storing its trailer fields
+    ///
+    /// Returns the bytes that were received past the end of the body,
+    /// see RequestBody::finish.
+    pub fn finish_body(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let (leftover, trailers) = mem::take(&mut self.body_stream).finish()?;
+        self.trailers = trailers;
+
+        Ok(leftover)
     }
 
     /// Set Request HTTP method
@@ -121,26 +873,70 @@ impl<'a> Request<'a> {
         Ok(self)
     }
 
-    /// Set Request HTTP uri
+    /// Set Request HTTP uri, splitting off and parsing its query
     pub fn uri_mut(&mut self, uri: &'a str) -> &mut Self {
+        let (path, query, _fragment) = split_target(uri);
+
         self.uri = Path::new(uri);
+        self.path = path;
+        self.query = query.map(parse_query).unwrap_or_default();
+        self.query_string = query;
         self
     }
 
     /// Set Request HTTP version
-    pub fn version_mut(&mut self, version: &'a str) -> Result<&mut Self, Box<dyn Error>> {
+    ///
+    /// # Errors
+    ///
+    /// Returns a RequestError with StatusCode 505 for HTTP versions other than HTTP/1.1,
+    /// 400 if `version` is not of the form `HTTP/<digit>.<digit>`
+    pub fn version_mut(&mut self, version: &'a str) -> Result<&mut Self, RequestError> {
+        let digits = version.strip_prefix("HTTP/").map(str::as_bytes);
+        if !matches!(digits, Some([major, b'.', minor]) if major.is_ascii_digit() && minor.is_ascii_digit())
+        {
+            return Err(bad_request(&format!("Invalid HTTP version {}", version)));
+        }
         if version != "HTTP/1.1" {
-            return Err(format!("HTTP version {} is not supported.", version).into());
+            return Err(RequestError::new(
+                StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+                &format!("HTTP version {} is not supported", version),
+            ));
         }
         self.version = version;
         Ok(self)
     }
 
+    /// Add a Request header, keeping existing headers with the same name
+    ///
+    /// # Panics
+    ///
+    /// Panics if the field is invalid, see Headers::try_append
+    pub fn header_mut(&mut self, name: &'a str, value: &'a str) -> &mut Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Set Request body
+    pub fn body_mut(&mut self, body: Vec<u8>) -> &mut Self {
+        self.body = body;
+        self
+    }
+
+    /// Attach a body to be read from the stream on demand, see body_reader
+    pub fn body_stream_mut(&mut self, body: RequestBody<'a>) -> &mut Self {
+        self.body_stream = body;
+        self
+    }
+
     /// Create a new Request object with defaults:
     ///
     /// method: Method::Get
     /// uri: Path::new("/"),
     /// version: "HTTP/1.1",
+    /// headers: empty
+    /// body: empty
+    /// body_stream: empty
+    /// trailers: empty
     pub fn new() -> Self {
         Request::default()
     }
@@ -163,7 +959,14 @@ impl<'a> Default for Request<'a> {
         Request {
             method: Method::default(),
             uri: Path::new("/"),
+            path: "/",
+            query: Vec::new(),
+            query_string: None,
             version: "HTTP/1.1",
+            headers: Headers::new(),
+            body: Vec::new(),
+            body_stream: RequestBody::default(),
+            trailers: Headers::new(),
         }
     }
 }
@@ -1,27 +1,28 @@
-//! HTTP request type
+//! HTTP response type
 
 use std::error::Error;
-use std::io::ErrorKind;
-use std::{env, fmt, fs, str};
+use std::fs::File;
+use std::io::{self, BufWriter, ErrorKind, IoSlice, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::time::SystemTime;
+use std::{fmt, fs, str};
 
+use crate::autoindex;
+use crate::body::Body;
+use crate::conditional;
+use crate::config::Config;
+use crate::content::SNIFF_LENGTH;
+use crate::date::http_date;
+use crate::encoding::{self, ContentCoding, MAX_COMPRESS_SIZE, PRECOMPRESSED};
+use crate::range::{self, RangeError};
+use crate::uri::{normalize_path, percent_encode_path, percent_encode_query};
 use crate::ContentType;
 use crate::Method;
 use crate::Request;
 use crate::StatusCode;
 
-/// HTTP headers
-#[derive(Default)]
-pub struct Headers {
-    content_type: Option<ContentType>,
-}
-
-impl Headers {
-    /// Create new ResponseHeader
-    /// By default the content_type is None
-    pub fn new() -> Self {
-        Headers { content_type: None }
-    }
-}
+pub use crate::headers::Headers;
 
 /// HTTP Response
 ///
@@ -34,8 +35,10 @@ impl Headers {
 #[derive(Default)]
 pub struct Response {
     status: StatusCode,
-    body: Option<Vec<u8>>,
-    headers: Headers,
+    body: Option<Body>,
+    headers: Headers<'static>,
+    // Bodiless response whose length is not known, e.g. a compressed HEAD response
+    unknown_length: bool,
 }
 
 impl Response {
@@ -48,29 +51,401 @@ impl Response {
             status: StatusCode::OK,
             body: None,
             headers: Headers::new(),
+            unknown_length: false,
+        }
+    }
+
+    /// Start building a Response, see Builder
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    /// Create a 200 Response with a `text/plain` body
+    pub fn text<B: Into<String>>(body: B) -> Response {
+        Response::builder()
+            .content_type(ContentType::TEXT.with_charset("utf-8"))
+            .body(body.into())
+    }
+
+    /// Create a 200 Response with a `text/html` body
+    pub fn html<B: Into<String>>(body: B) -> Response {
+        Response::builder()
+            .content_type(ContentType::HTML.with_charset("utf-8"))
+            .body(body.into())
+    }
+
+    /// Create a 200 Response with an `application/json` body
+    ///
+    /// The body has to be serialised JSON already
+    pub fn json<B: Into<String>>(body: B) -> Response {
+        Response::builder()
+            .content_type(ContentType::JSON.with_charset("utf-8"))
+            .body(body.into())
+    }
+
+    /// Create a 302 Found Response redirecting to `location`
+    ///
+    /// Use the builder for the other redirection status codes, e.g. 301 or 307
+    ///
+    /// # Panics
+    ///
+    /// Panics if `location` contains CR, LF or NUL, see Headers::append
+    pub fn redirect(location: &str) -> Response {
+        Response::builder()
+            .status(StatusCode::FOUND)
+            .header("Location", location)
+            .build()
+    }
+
+    /// Create an empty 404 Not Found Response
+    pub fn not_found() -> Response {
+        Response::builder().status(StatusCode::NOT_FOUND).build()
+    }
+
+    /// Compress the body with the content coding the client prefers, see encoding::negotiate
+    ///
+    /// Only complete 200 bodies of at least `min_size` and at most MAX_COMPRESS_SIZE bytes
+    /// with a compressible Content-Type and no Content-Encoding yet are compressed,
+    /// `Vary: Accept-Encoding` is added to all of them.
+    /// A compressed body does not accept ranges, and only weakly matches its ETag.
+    /// The ETag is weakened even if compressing did not make the body smaller,
+    /// so that it does not depend on the contents.
+    pub fn compress_mut(&mut self, accept_encoding: &[&str], min_size: usize) -> &mut Self {
+        self.compress(accept_encoding, min_size, true)
+    }
+
+    /// Describe the compressed body like compress_mut, without compressing it
+    ///
+    /// For responses to HEAD requests: the negotiated Content-Encoding and Vary
+    /// are sent, but the body is dropped and Content-Length is left out
+    /// instead of compressing the body just to compute its length.
+    /// Compressing is assumed to make the body smaller.
+    pub fn compress_head_mut(&mut self, accept_encoding: &[&str], min_size: usize) -> &mut Self {
+        self.compress(accept_encoding, min_size, false)
+    }
+
+    fn compress(&mut self, accept_encoding: &[&str], min_size: usize, encode: bool) -> &mut Self {
+        let coding = match &self.body {
+            Some(body) if self.status == StatusCode::OK => {
+                compression(&self.headers, body.len(), accept_encoding, min_size)
+            }
+            _ => None,
+        };
+        let coding = match coding {
+            Some(coding) => coding,
+            None => return self,
+        };
+
+        vary_accept_encoding(&mut self.headers);
+        if coding == ContentCoding::Identity {
+            return self;
+        }
+        if let Some(etag) = self.headers.get("ETag") {
+            let weak = conditional::weak(etag);
+            self.headers.insert("ETag", weak);
+        }
+        if !encode {
+            self.body = None;
+            self.unknown_length = true;
+            self.headers.remove("Content-Length");
+            self.headers.remove("Accept-Ranges");
+            self.headers.insert("Content-Encoding", coding.as_str());
+            return self;
+        }
+
+        let body = match self.body.take().map(Body::into_bytes) {
+            Some(Ok(body)) => body,
+            _ => {
+                *self = Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .build();
+                return self;
+            }
+        };
+        // Keep the original body if compressing it does not make it smaller
+        let encoded = match coding.encode(&body) {
+            Ok(encoded) if encoded.len() < body.len() => encoded,
+            _ => {
+                self.body = Some(Body::Bytes(body));
+                return self;
+            }
+        };
+
+        self.body = Some(Body::Bytes(encoded));
+        self.headers.remove("Content-Length");
+        // Ranges would apply to the compressed bytes, which are not stable
+        self.headers.remove("Accept-Ranges");
+        self.headers.insert("Content-Encoding", coding.as_str());
+
+        self
+    }
+
+    /// Get Response status
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Set Response status
+    pub fn status_mut(&mut self, status: StatusCode) -> &mut Self {
+        self.status = status;
+        self
+    }
+
+    /// Get Response body, None for bodiless responses (e.g. to HEAD requests)
+    pub fn body(&self) -> Option<&Body> {
+        self.body.as_ref()
+    }
+
+    /// Set Response body
+    pub fn body_mut<B: Into<Body>>(&mut self, body: B) -> &mut Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Drop the body, announcing the length it would have had in Content-Length
+    ///
+    /// Responses to HEAD requests are built like responses to GET requests
+    /// and then lose their body, so that both carry the same headers.
+    pub fn drop_body_mut(&mut self) -> &mut Self {
+        if let Some(body) = self.body.take() {
+            if !self.headers.contains("Content-Length") {
+                self.headers.content_length_mut(body.len());
+            }
         }
+        self
+    }
+
+    /// Get Response headers
+    pub fn headers(&self) -> &Headers<'static> {
+        &self.headers
     }
 
-    /// Format Response object and return it as a Vec of bytes to write to a buffer
-    pub fn format_response(&mut self) -> Vec<u8> {
-        // Append Status-Line
+    /// Get mutable access to the Response headers
+    pub fn headers_mut(&mut self) -> &mut Headers<'static> {
+        &mut self.headers
+    }
+
+    /// Format the Status-Line and headers of the Response, up to the empty line ending the head
+    ///
+    /// Allocates a new buffer, see write_head to reuse one
+    pub fn format_head(&mut self) -> Vec<u8> {
+        let mut head = Vec::new();
+        self.write_head(&mut head);
+        head
+    }
+
+    /// Append the Status-Line and headers of the Response to `head`,
+    /// up to the empty line ending the head
+    ///
+    /// Content-Length and Date are added if they are missing.
+    pub fn write_head(&mut self, head: &mut Vec<u8>) {
         // Status-Line = HTTP-Version SP Status-Code SP Reason-Phrase CRLF
-        let mut result = format!("HTTP/1.1 {}\r\n", self.status);
+        write!(
+            head,
+            "HTTP/1.1 {} {}\r\n",
+            self.status.as_u16(),
+            self.status.canonical_reason().unwrap_or("")
+        )
+        .expect("Writing to a Vec can not fail");
 
-        // Append Content-Type entity-header
-        if let Some(content_type) = &self.headers.content_type {
-            result = format!("{}Content-type: {}\r\n\r\n", result, content_type.as_str());
+        // Append Date general-header, unless set explicitly
+        if !self.headers.contains("Date") {
+            head.extend_from_slice(b"Date: ");
+            head.extend_from_slice(http_date(SystemTime::now()).as_bytes());
+            head.extend_from_slice(b"\r\n");
         }
 
-        // Append body (if file)
-        let mut bytes = result.as_bytes().to_vec();
-        if self.body.is_some() {
-            let body = self.body.as_mut().unwrap();
+        // Content-Length entity-header: persistent connections rely on it
+        // to find the end of the body. HEAD responses announce the length
+        // the body would have had, 1xx and 204 responses must not send it.
+        // 304 responses have no body either, they only send it when set explicitly.
+        if self.status.is_informational() || self.status == StatusCode::NO_CONTENT {
+            self.headers.remove("Content-Length");
+        } else if !self.headers.contains("Content-Length")
+            && self.status != StatusCode::NOT_MODIFIED
+            && !self.unknown_length
+        {
+            let length = self.body.as_ref().map_or(0, Body::len);
+            self.headers.content_length_mut(length);
+        }
+
+        // Append general-, response- and entity-headers
+        for (name, value) in self.headers.iter() {
+            head.extend_from_slice(name.as_bytes());
+            head.extend_from_slice(b": ");
+            head.extend_from_slice(value.as_bytes());
+            head.extend_from_slice(b"\r\n");
+        }
+
+        // Empty line ends the head
+        head.extend_from_slice(b"\r\n");
+    }
+
+    /// Write the Response to `writer`, streaming the body, see Body::write_to
+    ///
+    /// The head is serialised into `head`, which is cleared first so that it can be
+    /// reused across responses. Bodies in memory are written together with the head
+    /// in one vectored write, without copying them after it. A buffered `writer`
+    /// may still copy both into its buffer, send_to avoids that for sockets.
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors reading the body or writing to `writer`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use linda::response::Response;
+    ///
+    /// let mut response = Response::text("Hello");
+    /// let (mut written, mut head) = (Vec::new(), Vec::new());
+    /// response.write_to(&mut written, &mut head).unwrap();
+    ///
+    /// assert!(written.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    /// assert!(written.ends_with(b"Content-Length: 5\r\n\r\nHello"));
+    /// assert!(written.starts_with(&head));
+    /// ```
+    pub fn write_to<W: Write>(&mut self, writer: &mut W, head: &mut Vec<u8>) -> io::Result<()> {
+        self.write_with(writer, head, write_all_vectored, |body, writer| {
+            body.write_to(writer)
+        })
+    }
+
+    /// Write the Response to the socket behind `writer`, with zero-copy file bodies
+    /// where the platform supports it, see write_to and Body::send_to
+    ///
+    /// Responses that fit in the spare capacity of `writer` are buffered, so that
+    /// responses to pipelined requests go out together. Larger in-memory responses
+    /// are written straight to the socket once `writer` is flushed, without being
+    /// copied into its buffer.
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors reading the body or writing to the socket
+    pub fn send_to(
+        &mut self,
+        writer: &mut BufWriter<&TcpStream>,
+        head: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        self.write_with(
+            writer,
+            head,
+            |writer, slices| {
+                let len: usize = slices.iter().map(|slice| slice.len()).sum();
+                if len <= writer.capacity() - writer.buffer().len() {
+                    return write_all_vectored(writer, slices);
+                }
+
+                writer.flush()?;
+                let mut stream: &TcpStream = writer.get_ref();
+                write_all_vectored(&mut stream, slices)
+            },
+            |body, writer| body.send_to(writer),
+        )
+    }
+
+    /// Write the head, together with the body with `write_bytes` if it is in memory,
+    /// or followed by the body with `write_body`
+    fn write_with<W: Write>(
+        &mut self,
+        writer: &mut W,
+        head: &mut Vec<u8>,
+        write_bytes: impl FnOnce(&mut W, &mut [IoSlice]) -> io::Result<()>,
+        write_body: impl FnOnce(&mut Body, &mut W) -> io::Result<()>,
+    ) -> io::Result<()> {
+        head.clear();
+        self.write_head(head);
 
-            bytes.append(body);
+        match self.body.take() {
+            Some(Body::Bytes(bytes)) => {
+                write_bytes(writer, &mut [IoSlice::new(head), IoSlice::new(&bytes)])
+            }
+            Some(mut body) => {
+                writer.write_all(head)?;
+                write_body(&mut body, writer)
+            }
+            None => writer.write_all(head),
         }
+    }
+}
 
-        bytes
+/// Write all of `slices` to `writer`, with as few vectored writes as it accepts
+fn write_all_vectored<W: Write>(writer: &mut W, mut slices: &mut [IoSlice]) -> io::Result<()> {
+    while slices.iter().any(|slice| !slice.is_empty()) {
+        match writer.write_vectored(slices) {
+            Ok(0) => return Err(io::Error::from(ErrorKind::WriteZero)),
+            Ok(written) => IoSlice::advance_slices(&mut slices, written),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Builder for Response values
+///
+/// # Examples
+///
+/// ```
+/// use linda::response::Response;
+/// use linda::status::StatusCode;
+///
+/// let response = Response::builder()
+///     .status(StatusCode::CREATED)
+///     .header("Location", "/items/1")
+///     .header("Cache-Control", "no-store")
+///     .body("created");
+///
+/// assert_eq!(response.status(), StatusCode::CREATED);
+/// assert_eq!(response.headers().get("location"), Some("/items/1"));
+/// assert_eq!(response.body().and_then(|body| body.as_bytes()), Some(&b"created"[..]));
+/// ```
+#[derive(Default)]
+pub struct Builder {
+    response: Response,
+}
+
+impl Builder {
+    /// Create a Builder for a 200 Response without headers or body
+    pub fn new() -> Self {
+        Builder::default()
+    }
+
+    /// Set the Response status
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.response.status = status;
+        self
+    }
+
+    /// Add a header, keeping existing headers with the same name
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name is not a token or the value contains CR, LF or NUL,
+    /// see Headers::append
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.response
+            .headers
+            .append(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Set the Content-Type header
+    pub fn content_type(mut self, content_type: ContentType) -> Self {
+        self.response.headers.content_type_mut(content_type);
+        self
+    }
+
+    /// Finish the Response with a body
+    pub fn body<B: Into<Body>>(mut self, body: B) -> Response {
+        self.response.body = Some(body.into());
+        self.response
+    }
+
+    /// Finish the Response without a body
+    pub fn build(self) -> Response {
+        self.response
     }
 }
 
@@ -81,53 +456,362 @@ impl fmt::Display for Response {
     }
 }
 
-fn add_file(path: &str, head: bool) -> Result<Response, Box<dyn Error>> {
-    let mut root = String::from("/var/www");
+fn add_file(request: &Request, config: &Config) -> Result<Response, Box<dyn Error>> {
+    let head = *request.method() == Method::HEAD;
+    let root = config.root();
 
-    if env::var("LINDA_ROOT").is_ok() {
-        root = env::var("LINDA_ROOT").unwrap();
+    // Decode and resolve dot segments, refusing paths that would leave the root
+    let path = match request.path().map(|path| normalize_path(&path)) {
+        Ok(Ok(path)) => path,
+        _ => return Ok(Response::builder().status(StatusCode::BAD_REQUEST).build()),
     };
 
-    let mut path = path.to_string();
-    if path == "/" {
-        path.push_str("index.html");
+    // Hidden files and directories such as .git/ or .env are not served by default
+    if !config.serve_hidden() && is_hidden(&path) {
+        return Ok(not_found(root, config));
     }
-    path = format!("{}{}", root, path);
 
-    let contents = fs::read(&path);
+    let mut file = format!("{}{}", root, path);
+
+    // Symbolic links below the root may still point outside of it
+    if !is_contained(root, &file) {
+        return Ok(Response::builder().status(StatusCode::FORBIDDEN).build());
+    }
+
+    // Directories are served through their index file, at a URI ending with a slash
+    // so that relative links in the index resolve below the directory
+    if Path::new(&file).is_dir() {
+        if !path.ends_with('/') {
+            let mut location = format!("{}/", percent_encode_path(&path));
+            if let Some(query) = request.query_string() {
+                location.push('?');
+                location.push_str(&percent_encode_query(query));
+            }
+
+            let mut response = Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .build();
+            if response.headers.try_insert("Location", location).is_err() {
+                response.status_mut(StatusCode::BAD_REQUEST);
+            }
+            return Ok(response);
+        }
+
+        let index = config
+            .index_files()
+            .iter()
+            .map(|index| format!("{}{}", file, index))
+            .find(|index| Path::new(index).is_file());
+        match index {
+            Some(index) if is_contained(root, &index) => file = index,
+            Some(_) => return Ok(Response::builder().status(StatusCode::FORBIDDEN).build()),
+            None if config.autoindex() => return Ok(autoindex(request, &path, &file, root)),
+            None => return Ok(not_found(root, config)),
+        }
+    }
+
+    // Serve a precompressed sibling such as `app.css.br` in place of the file
+    // if the client accepts its encoding
+    let precompressed: Vec<ContentCoding> = PRECOMPRESSED
+        .iter()
+        .copied()
+        .filter(|_| config.precompressed() && Path::new(&file).is_file())
+        .filter(|coding| {
+            let sibling = encoded_path(&file, *coding);
+            Path::new(&sibling).is_file() && is_contained(root, &sibling)
+        })
+        .collect();
+    let coding = encoding::negotiate(&request.headers_all("Accept-Encoding"), &precompressed);
+    let encoded = encoded_path(&file, coding);
+
+    // Validators come from the metadata, so that preconditions are evaluated
+    // before the body is read
+    let opened = File::open(&encoded).and_then(|file| {
+        let metadata = file.metadata()?;
+        Ok((file, metadata))
+    });
+    let (mut contents, metadata) = match opened {
+        Ok(opened) => opened,
+        Err(e) => return Ok(io_error(e, root, config)),
+    };
+    let etag = conditional::etag(&metadata);
+    // Last-Modified must not be in the future
+    let modified = metadata
+        .modified()
+        .ok()
+        .map(|modified| modified.min(SystemTime::now()));
 
     let mut response = Response::new();
 
-    match contents {
-        Ok(contents) => {
-            // check if method type is not HEAD
-            if !head {
-                response.body = Some(contents);
+    // Unknown and missing extensions are sniffed if enabled,
+    // then fall back to the configured type
+    let content_type = match config.mime_types().from_path(&file) {
+        Some(content_type) => content_type.clone(),
+        None => Some(&mut contents)
+            .filter(|_| config.sniff_content_type() && coding == ContentCoding::Identity)
+            .and_then(|contents| {
+                let mut start = Vec::with_capacity(SNIFF_LENGTH);
+                contents
+                    .take(SNIFF_LENGTH as u64)
+                    .read_to_end(&mut start)
+                    .ok()?;
+                ContentType::sniff(&start)
+            })
+            .unwrap_or_else(|| config.default_content_type().clone()),
+    };
+    let content_type = with_default_charset(&content_type, config);
+    response.headers.content_type_mut(content_type.clone());
+    if coding != ContentCoding::Identity {
+        response.headers.insert("Content-Encoding", coding.as_str());
+    }
+
+    // Representations compressed later on by compress_mut vary and only have
+    // a weak ETag, a 304 answering a conditional request for them has to agree
+    let compressed = Some(&response.headers)
+        .filter(|_| config.compression())
+        .and_then(|headers| {
+            compression(
+                headers,
+                metadata.len(),
+                &request.headers_all("Accept-Encoding"),
+                config.compression_min_size(),
+            )
+        });
+    if !precompressed.is_empty() || compressed.is_some() {
+        vary_accept_encoding(&mut response.headers);
+    }
+    let etag = match compressed {
+        Some(coding) if coding != ContentCoding::Identity => conditional::weak(&etag),
+        _ => etag,
+    };
+
+    if let Some(status) = conditional::evaluate(request, &etag, modified) {
+        let mut conditional = Response::builder().status(status).build();
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(vary) = response.headers.get("Vary") {
+                conditional.headers.insert("Vary", vary.to_string());
             }
+            add_validators(&mut conditional, &etag, modified);
+        }
+        return Ok(conditional);
+    }
 
-            // Get file extension
-            let ext = path.split('.').last().unwrap_or("");
-            response.headers.content_type = Some(ContentType::from_ext_str(ext)?);
+    response.headers.insert("Accept-Ranges", "bytes");
+    add_validators(&mut response, &etag, modified);
 
-            Ok(response)
+    let contents = Body::File {
+        file: contents,
+        offset: 0,
+        len: metadata.len(),
+    };
+    // Only GET defines range handling
+    if head {
+        response.body = Some(contents);
+    } else {
+        add_ranges(
+            &mut response,
+            request,
+            contents,
+            &content_type,
+            Some(&etag),
+            modified,
+        )?;
+    }
+
+    Ok(response)
+}
+
+/// Get the path of the sibling of `file` precompressed with `coding`, `file` for identity
+fn encoded_path(file: &str, coding: ContentCoding) -> String {
+    format!("{}{}", file, coding.extension().unwrap_or(""))
+}
+
+/// Add Accept-Encoding to the Vary header unless it is already listed
+fn vary_accept_encoding(headers: &mut Headers<'static>) {
+    let listed = headers.get_all("Vary").iter().any(|vary| {
+        vary.split(',')
+            .any(|name| name.trim().eq_ignore_ascii_case("Accept-Encoding"))
+    });
+    if !listed {
+        headers.append("Vary", "Accept-Encoding");
+    }
+}
+
+/// Get the content coding compress_mut picks for a 200 response with a body of `len` bytes
+///
+/// None if the body is not compressed whatever the client accepts,
+/// ContentCoding::Identity if the client accepts neither gzip nor deflate.
+fn compression(
+    headers: &Headers,
+    len: u64,
+    accept_encoding: &[&str],
+    min_size: usize,
+) -> Option<ContentCoding> {
+    let compressible = !headers.contains("Content-Encoding")
+        && headers
+            .content_type()
+            .and_then(|content_type| ContentType::new(content_type).ok())
+            .is_some_and(|content_type| content_type.is_compressible());
+    if !compressible || len < min_size as u64 || len > MAX_COMPRESS_SIZE {
+        return None;
+    }
+
+    Some(encoding::negotiate(
+        accept_encoding,
+        &[ContentCoding::Gzip, ContentCoding::Deflate],
+    ))
+}
+
+/// Set the ETag and Last-Modified headers
+fn add_validators(response: &mut Response, etag: &str, modified: Option<SystemTime>) {
+    response.headers.insert("ETag", etag.to_string());
+    if let Some(modified) = modified {
+        response
+            .headers
+            .insert("Last-Modified", http_date(modified));
+    }
+}
+
+/// Create the Response for a file that could not be read
+fn io_error(e: io::Error, root: &str, config: &Config) -> Response {
+    match e.kind() {
+        // A path going through a regular file, e.g. `/index.html/`
+        ErrorKind::NotFound | ErrorKind::NotADirectory => not_found(root, config),
+        ErrorKind::PermissionDenied => Response::builder().status(StatusCode::FORBIDDEN).build(),
+        _ => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .build(),
+    }
+}
+
+/// Set the body to the ranges of `contents` asked for by a Range header, or to all of it
+///
+/// A single range is sent as is, several ranges as multipart/byteranges.
+/// The Range header is ignored if it is invalid or an If-Range validator no longer matches.
+///
+/// # Errors
+///
+/// Propagates errors slicing `contents`, see Body::slice
+fn add_ranges(
+    response: &mut Response,
+    request: &Request,
+    contents: Body,
+    content_type: &ContentType,
+    etag: Option<&str>,
+    modified: Option<SystemTime>,
+) -> io::Result<()> {
+    let length = contents.len();
+    let ranges = match request.header("Range") {
+        Some(range)
+            if request
+                .header("If-Range")
+                .is_none_or(|if_range| range::if_range_matches(if_range, etag, modified)) =>
+        {
+            range::parse_range(range, length)
         }
-        Err(e) => {
-            response.status = match e.kind() {
-                ErrorKind::NotFound => {
-                    // Set response body to 404.html if file not found
-                    // check if method type is not HEAD
-                    if !head {
-                        response.body =
-                            Some(fs::read(format!("{}/404.html", root)).unwrap_or_else(|_| vec![]));
-                        response.headers.content_type = Some(ContentType::HTML);
-                    }
-                    StatusCode::NOT_FOUND
-                }
-                ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            };
-            Ok(response)
+        _ => Err(RangeError::Invalid(String::new())),
+    };
+
+    match ranges {
+        Ok(ranges) => {
+            response.status = StatusCode::PARTIAL_CONTENT;
+
+            if let [range] = ranges[..] {
+                response
+                    .headers
+                    .insert("Content-Range", range.content_range(length));
+                response.body = Some(contents.slice(range.start(), range.len())?);
+            } else {
+                let boundary = range::boundary();
+                response.headers.content_type_mut(
+                    ContentType::new(&format!("multipart/byteranges; boundary={}", boundary))
+                        .expect("Invalid multipart/byteranges media type"),
+                );
+                response.body = Some(range::multipart_byteranges(
+                    &contents,
+                    &ranges,
+                    content_type,
+                    &boundary,
+                )?);
+            }
+        }
+        Err(RangeError::Unsatisfiable) => {
+            response.status = StatusCode::REQUEST_RANGE_NOT_SATISFIABLE;
+            response.headers.remove("Content-Type");
+            response
+                .headers
+                .insert("Content-Range", format!("bytes */{}", length));
         }
+        Err(RangeError::Invalid(_)) => response.body = Some(contents),
+    }
+
+    Ok(())
+}
+
+/// Create a listing of `directory`, as JSON if the client prefers it over HTML
+fn autoindex(request: &Request, path: &str, directory: &str, root: &str) -> Response {
+    let entries = match autoindex::read_entries(directory, root) {
+        Ok(entries) => entries,
+        Err(_) => {
+            return Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .build()
+        }
+    };
+
+    let accept = request.headers_all("Accept");
+    // On equal quality, e.g. for `application/json, */*`, the explicitly named type wins
+    let mut response =
+        if ContentType::JSON.preference(&accept) > ContentType::HTML.preference(&accept) {
+            Response::json(autoindex::render_json(path, &entries))
+        } else {
+            Response::html(autoindex::render_html(path, &entries))
+        };
+    response.headers.append("Vary", "Accept");
+
+    response
+}
+
+/// Create a 404 Response with the 404.html page of the document root as body, if any
+fn not_found(root: &str, config: &Config) -> Response {
+    let contents = fs::read(format!("{}/404.html", root)).unwrap_or_else(|_| vec![]);
+
+    let mut response = Response::new();
+    response.status = StatusCode::NOT_FOUND;
+    response
+        .headers
+        .content_type_mut(with_default_charset(&ContentType::HTML, config));
+    response.body = Some(Body::Bytes(contents));
+
+    response
+}
+
+/// Add the configured charset to textual types that do not carry one
+fn with_default_charset(content_type: &ContentType, config: &Config) -> ContentType {
+    match config.default_charset() {
+        Some(charset) if content_type.is_textual() && content_type.param("charset").is_none() => {
+            content_type.with_charset(charset)
+        }
+        _ => content_type.clone(),
+    }
+}
+
+/// Whether a segment of the normalised `path` starts with `.`
+///
+/// The well-known URIs of RFC 8615, e.g. `/.well-known/security.txt`, are not hidden
+fn is_hidden(path: &str) -> bool {
+    path.split('/')
+        .enumerate()
+        .any(|(i, segment)| segment.starts_with('.') && (i, segment) != (1, ".well-known"))
+}
+
+/// Whether `path` resolves to a file inside `root`, following symbolic links
+///
+/// Paths that can not be resolved count as contained, reading them fails anyway
+fn is_contained(root: &str, path: &str) -> bool {
+    match (fs::canonicalize(root), fs::canonicalize(path)) {
+        (Ok(root), Ok(path)) => path.starts_with(root),
+        _ => true,
     }
 }
 
@@ -135,14 +819,10 @@ fn add_file(path: &str, head: bool) -> Result<Response, Box<dyn Error>> {
 ///
 /// # Error
 ///
-/// Should not error, except for rare cases when the URI string is not valid UTF-8
-pub fn response(request: &Request) -> Result<Response, Box<dyn Error>> {
+/// Should not error, paths that can not be decoded are answered with 400
+pub fn response(request: &Request, config: &Config) -> Result<Response, Box<dyn Error>> {
     match *request.method() {
-        Method::GET => add_file(
-            request.uri().to_str().expect("Invalid file URI UTF8"),
-            false,
-        ),
-        Method::HEAD => add_file(request.uri().to_str().expect("Invalid file URI UTF8"), true),
+        Method::GET | Method::HEAD => add_file(request, config),
         _ => {
             let mut response = Response::new();
             response.status = StatusCode::NOT_IMPLEMENTED;
@@ -0,0 +1,53 @@
+//! Zero-copy transmission of files to sockets with sendfile(2)
+//!
+//! The kernel moves the file's pages to the socket directly, without copying
+//! them through a userspace buffer. Only available on Linux, Body::send_to
+//! falls back to buffered copies elsewhere or when the file system refuses.
+
+use std::fs::File;
+use std::io::{self, ErrorKind};
+use std::net::TcpStream;
+use std::os::unix::io::AsRawFd;
+
+/// Largest count a single sendfile(2) call transfers
+const MAX_SEND: u64 = 0x7fff_f000;
+
+/// Send `len` bytes of `file` starting at `offset` to `socket`
+///
+/// The position of `file` is left untouched. Returns the number of bytes sent
+/// when it stops early, which is less than `len` only if the file ended.
+///
+/// # Errors
+///
+/// * ErrorKind::Unsupported if nothing was sent because sendfile(2)
+///   is not supported for this file, buffered copies have to be used instead
+/// * Propagates other errors of sendfile(2), e.g. when the client disconnects
+pub fn send_file(file: &File, offset: u64, len: u64, socket: &TcpStream) -> io::Result<u64> {
+    let mut position = offset as libc::off_t;
+    let mut sent = 0;
+
+    while sent < len {
+        let count = (len - sent).min(MAX_SEND) as usize;
+        // Safety: both descriptors are open for the duration of the call
+        // and `position` outlives it
+        let result =
+            unsafe { libc::sendfile(socket.as_raw_fd(), file.as_raw_fd(), &mut position, count) };
+
+        match result {
+            0 => break,
+            n if n > 0 => sent += n as u64,
+            _ => {
+                let error = io::Error::last_os_error();
+                match error.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    Some(libc::EINVAL) | Some(libc::ENOSYS) if sent == 0 => {
+                        return Err(io::Error::new(ErrorKind::Unsupported, error))
+                    }
+                    _ => return Err(error),
+                }
+            }
+        }
+    }
+
+    Ok(sent)
+}
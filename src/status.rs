@@ -1,49 +1,294 @@
-use std::collections::HashMap;
-use std::fmt;
-
-#[derive(Hash, Eq, PartialEq, Clone)]
-#[allow(non_camel_case_types)]
-pub(super) enum StatusCode {
-    CONTINUE,
-    SWITCHING_PROTOCOLS,
-    OK,
-    CREATED,
-    ACCEPTED,
-    NON_AUTHORITATIVE_INFORMATION,
-    NO_CONTENT,
-    RESET_CONTENT,
-    PARTIAL_CONTENT,
-    MULTIPLE_CHOICES,
-    MOVED_PERMANENTLY,
-    FOUND,
-    SEE_OTHER,
-    NOT_MODIFIED,
-    USE_PROXY,
-    TEMPORARY_REDIRECT,
-    BAD_REQUEST,
-    UNAUTHORIZED,
-    PAYMENT_REQUIRED,
-    FORBIDDEN,
-    NOT_FOUND,
-    METHOD_NOT_ALLOWED,
-    NOT_ACCEPTABLE,
-    PROXY_AUTHENTICATION_REQUIRED,
-    REQUEST_TIME_OUT,
-    CONFLICT,
-    GONE,
-    LENGTH_REQUIRED,
-    PRECONDITION_FAILED,
-    REQUEST_ENTITY_TOO_LARGE,
-    REQUEST_URI_TOO_LARGE,
-    UNSUPPORTED_MEDIA_TYPE,
-    REQUEST_RANGE_NOT_SATISFIABLE,
-    EXPECTATION_FAILED,
-    INTERNAL_SERVER_ERROR,
-    NOT_IMPLEMENTED,
-    BAD_GATEWAY,
-    SERVICE_UNAVAILABLE,
-    GATEWAY_TIME_OUT,
-    HTTP_VERSION_NOT_SUPPORTED,
+//! HTTP response status codes
+
+use std::{error, fmt};
+
+/// Returned when a number is not a valid three digit Status-Code
+#[derive(Debug)]
+pub struct InvalidStatusCode(u16);
+
+impl fmt::Display for InvalidStatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid status code: {}", self.0)
+    }
+}
+
+impl error::Error for InvalidStatusCode {}
+
+/// Response Status-Code
+///
+/// Contains constants for the registered status codes, e.g. OK, NOT_FOUND.
+/// Any other three digit code can be created with from_u16.
+///
+/// # Examples
+///
+/// ```
+/// use linda::status::StatusCode;
+/// # use std::error::Error;
+///
+/// assert_eq!(StatusCode::NOT_FOUND.as_u16(), 404);
+/// assert_eq!(StatusCode::NOT_FOUND.canonical_reason(), Some("Not Found"));
+/// assert_eq!(StatusCode::NOT_FOUND.to_string(), "404 Not Found");
+/// assert!(StatusCode::NOT_FOUND.is_client_error());
+///
+/// let code = StatusCode::from_u16(299)?;
+/// assert!(code.is_success());
+/// assert_eq!(code.canonical_reason(), None);
+///
+/// # Ok::<(), Box<dyn Error>>(())
+/// ```
+#[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+pub struct StatusCode(u16);
+
+impl StatusCode {
+    /// 100 Continue
+    pub const CONTINUE: StatusCode = StatusCode(100);
+
+    /// 101 Switching Protocols
+    pub const SWITCHING_PROTOCOLS: StatusCode = StatusCode(101);
+
+    /// 200 OK
+    pub const OK: StatusCode = StatusCode(200);
+
+    /// 201 Created
+    pub const CREATED: StatusCode = StatusCode(201);
+
+    /// 202 Accepted
+    pub const ACCEPTED: StatusCode = StatusCode(202);
+
+    /// 203 Non-Authoritative Information
+    pub const NON_AUTHORITATIVE_INFORMATION: StatusCode = StatusCode(203);
+
+    /// 204 No Content
+    pub const NO_CONTENT: StatusCode = StatusCode(204);
+
+    /// 205 Reset Content
+    pub const RESET_CONTENT: StatusCode = StatusCode(205);
+
+    /// 206 Partial Content
+    pub const PARTIAL_CONTENT: StatusCode = StatusCode(206);
+
+    /// 300 Multiple Choices
+    pub const MULTIPLE_CHOICES: StatusCode = StatusCode(300);
+
+    /// 301 Moved Permanently
+    pub const MOVED_PERMANENTLY: StatusCode = StatusCode(301);
+
+    /// 302 Found
+    pub const FOUND: StatusCode = StatusCode(302);
+
+    /// 303 See Other
+    pub const SEE_OTHER: StatusCode = StatusCode(303);
+
+    /// 304 Not Modified
+    pub const NOT_MODIFIED: StatusCode = StatusCode(304);
+
+    /// 305 Use Proxy
+    pub const USE_PROXY: StatusCode = StatusCode(305);
+
+    /// 307 Temporary Redirect
+    pub const TEMPORARY_REDIRECT: StatusCode = StatusCode(307);
+
+    /// 308 Permanent Redirect
+    pub const PERMANENT_REDIRECT: StatusCode = StatusCode(308);
+
+    /// 400 Bad Request
+    pub const BAD_REQUEST: StatusCode = StatusCode(400);
+
+    /// 401 Unauthorized
+    pub const UNAUTHORIZED: StatusCode = StatusCode(401);
+
+    /// 402 Payment Required
+    pub const PAYMENT_REQUIRED: StatusCode = StatusCode(402);
+
+    /// 403 Forbidden
+    pub const FORBIDDEN: StatusCode = StatusCode(403);
+
+    /// 404 Not Found
+    pub const NOT_FOUND: StatusCode = StatusCode(404);
+
+    /// 405 Method Not Allowed
+    pub const METHOD_NOT_ALLOWED: StatusCode = StatusCode(405);
+
+    /// 406 Not Acceptable
+    pub const NOT_ACCEPTABLE: StatusCode = StatusCode(406);
+
+    /// 407 Proxy Authentication Required
+    pub const PROXY_AUTHENTICATION_REQUIRED: StatusCode = StatusCode(407);
+
+    /// 408 Request Timeout
+    pub const REQUEST_TIME_OUT: StatusCode = StatusCode(408);
+
+    /// 409 Conflict
+    pub const CONFLICT: StatusCode = StatusCode(409);
+
+    /// 410 Gone
+    pub const GONE: StatusCode = StatusCode(410);
+
+    /// 411 Length Required
+    pub const LENGTH_REQUIRED: StatusCode = StatusCode(411);
+
+    /// 412 Precondition Failed
+    pub const PRECONDITION_FAILED: StatusCode = StatusCode(412);
+
+    /// 413 Payload Too Large
+    pub const REQUEST_ENTITY_TOO_LARGE: StatusCode = StatusCode(413);
+
+    /// 414 URI Too Long
+    pub const REQUEST_URI_TOO_LARGE: StatusCode = StatusCode(414);
+
+    /// 415 Unsupported Media Type
+    pub const UNSUPPORTED_MEDIA_TYPE: StatusCode = StatusCode(415);
+
+    /// 416 Range Not Satisfiable
+    pub const REQUEST_RANGE_NOT_SATISFIABLE: StatusCode = StatusCode(416);
+
+    /// 417 Expectation Failed
+    pub const EXPECTATION_FAILED: StatusCode = StatusCode(417);
+
+    /// 418 I'm a teapot
+    pub const IM_A_TEAPOT: StatusCode = StatusCode(418);
+
+    /// 421 Misdirected Request
+    pub const MISDIRECTED_REQUEST: StatusCode = StatusCode(421);
+
+    /// 422 Unprocessable Entity
+    pub const UNPROCESSABLE_ENTITY: StatusCode = StatusCode(422);
+
+    /// 426 Upgrade Required
+    pub const UPGRADE_REQUIRED: StatusCode = StatusCode(426);
+
+    /// 428 Precondition Required
+    pub const PRECONDITION_REQUIRED: StatusCode = StatusCode(428);
+
+    /// 429 Too Many Requests
+    pub const TOO_MANY_REQUESTS: StatusCode = StatusCode(429);
+
+    /// 431 Request Header Fields Too Large
+    pub const REQUEST_HEADER_FIELDS_TOO_LARGE: StatusCode = StatusCode(431);
+
+    /// 451 Unavailable For Legal Reasons
+    pub const UNAVAILABLE_FOR_LEGAL_REASONS: StatusCode = StatusCode(451);
+
+    /// 500 Internal Server Error
+    pub const INTERNAL_SERVER_ERROR: StatusCode = StatusCode(500);
+
+    /// 501 Not Implemented
+    pub const NOT_IMPLEMENTED: StatusCode = StatusCode(501);
+
+    /// 502 Bad Gateway
+    pub const BAD_GATEWAY: StatusCode = StatusCode(502);
+
+    /// 503 Service Unavailable
+    pub const SERVICE_UNAVAILABLE: StatusCode = StatusCode(503);
+
+    /// 504 Gateway Timeout
+    pub const GATEWAY_TIME_OUT: StatusCode = StatusCode(504);
+
+    /// 505 HTTP Version Not Supported
+    pub const HTTP_VERSION_NOT_SUPPORTED: StatusCode = StatusCode(505);
+
+    /// 511 Network Authentication Required
+    pub const NETWORK_AUTHENTICATION_REQUIRED: StatusCode = StatusCode(511);
+
+    /// Create a StatusCode from its number
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidStatusCode if the number does not have three digits
+    pub fn from_u16(code: u16) -> Result<StatusCode, InvalidStatusCode> {
+        if (100..1000).contains(&code) {
+            Ok(StatusCode(code))
+        } else {
+            Err(InvalidStatusCode(code))
+        }
+    }
+
+    /// Return the Status-Code as a number
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    /// Return the Reason-Phrase registered for this code, None if it is not registered
+    pub fn canonical_reason(&self) -> Option<&'static str> {
+        Some(match self.0 {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            203 => "Non-Authoritative Information",
+            204 => "No Content",
+            205 => "Reset Content",
+            206 => "Partial Content",
+            300 => "Multiple Choices",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            305 => "Use Proxy",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            402 => "Payment Required",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            407 => "Proxy Authentication Required",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            410 => "Gone",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Payload Too Large",
+            414 => "URI Too Long",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            417 => "Expectation Failed",
+            418 => "I'm a teapot",
+            421 => "Misdirected Request",
+            422 => "Unprocessable Entity",
+            426 => "Upgrade Required",
+            428 => "Precondition Required",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            451 => "Unavailable For Legal Reasons",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            505 => "HTTP Version Not Supported",
+            511 => "Network Authentication Required",
+            _ => return None,
+        })
+    }
+
+    /// Whether this is a 1xx informational status
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    /// Whether this is a 2xx success status
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    /// Whether this is a 3xx redirection status
+    pub fn is_redirection(&self) -> bool {
+        (300..400).contains(&self.0)
+    }
+
+    /// Whether this is a 4xx client error status
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    /// Whether this is a 5xx server error status
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.0)
+    }
 }
 
 impl Default for StatusCode {
@@ -52,53 +297,13 @@ impl Default for StatusCode {
     }
 }
 
+/// Formats as Status-Code SP Reason-Phrase, e.g. `404 Not Found`,
+/// or just the Status-Code if it has no registered Reason-Phrase
 impl fmt::Display for StatusCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let codes: HashMap<StatusCode, i32> = [
-            (StatusCode::CONTINUE, 100),
-            (StatusCode::SWITCHING_PROTOCOLS, 101),
-            (StatusCode::OK, 200),
-            (StatusCode::CREATED, 201),
-            (StatusCode::ACCEPTED, 202),
-            (StatusCode::NON_AUTHORITATIVE_INFORMATION, 203),
-            (StatusCode::NO_CONTENT, 204),
-            (StatusCode::RESET_CONTENT, 205),
-            (StatusCode::PARTIAL_CONTENT, 206),
-            (StatusCode::MULTIPLE_CHOICES, 300),
-            (StatusCode::MOVED_PERMANENTLY, 301),
-            (StatusCode::FOUND, 302),
-            (StatusCode::SEE_OTHER, 303),
-            (StatusCode::NOT_MODIFIED, 304),
-            (StatusCode::USE_PROXY, 305),
-            (StatusCode::TEMPORARY_REDIRECT, 307),
-            (StatusCode::BAD_REQUEST, 400),
-            (StatusCode::UNAUTHORIZED, 401),
-            (StatusCode::PAYMENT_REQUIRED, 402),
-            (StatusCode::FORBIDDEN, 403),
-            (StatusCode::NOT_FOUND, 404),
-            (StatusCode::METHOD_NOT_ALLOWED, 405),
-            (StatusCode::NOT_ACCEPTABLE, 406),
-            (StatusCode::PROXY_AUTHENTICATION_REQUIRED, 407),
-            (StatusCode::REQUEST_TIME_OUT, 408),
-            (StatusCode::CONFLICT, 409),
-            (StatusCode::GONE, 410),
-            (StatusCode::LENGTH_REQUIRED, 411),
-            (StatusCode::PRECONDITION_FAILED, 412),
-            (StatusCode::REQUEST_ENTITY_TOO_LARGE, 413),
-            (StatusCode::REQUEST_URI_TOO_LARGE, 414),
-            (StatusCode::UNSUPPORTED_MEDIA_TYPE, 415),
-            (StatusCode::REQUEST_RANGE_NOT_SATISFIABLE, 416),
-            (StatusCode::EXPECTATION_FAILED, 417),
-            (StatusCode::INTERNAL_SERVER_ERROR, 500),
-            (StatusCode::NOT_IMPLEMENTED, 501),
-            (StatusCode::BAD_GATEWAY, 502),
-            (StatusCode::SERVICE_UNAVAILABLE, 503),
-            (StatusCode::GATEWAY_TIME_OUT, 504),
-            (StatusCode::HTTP_VERSION_NOT_SUPPORTED, 505),
-        ]
-        .iter()
-        .cloned()
-        .collect();
-        write!(f, "{}", codes.get(self).unwrap())
+        match self.canonical_reason() {
+            Some(reason) => write!(f, "{} {}", self.0, reason),
+            None => write!(f, "{}", self.0),
+        }
     }
 }
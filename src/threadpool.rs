@@ -1,6 +1,7 @@
 //! ThreadPool implementation
 
 use log::info;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 
@@ -15,6 +16,43 @@ enum Message {
 pub struct ThreadPool {
     workers: Vec<Worker>,
     sender: mpsc::Sender<Message>,
+    backlog: Backlog,
+}
+
+/// Number of jobs waiting for a worker of a ThreadPool
+///
+/// Clones share the count, so that running jobs can tell whether others wait.
+///
+/// # Examples
+///
+/// ```
+/// use linda::threadpool::ThreadPool;
+/// use std::sync::mpsc;
+///
+/// let pool = ThreadPool::new(1);
+/// let backlog = pool.backlog();
+/// let (sender, receiver) = mpsc::channel::<()>();
+///
+/// // Keep the only worker busy until told to stop
+/// pool.execute(move || receiver.recv().unwrap());
+/// pool.execute(|| ());
+///
+/// assert!(!backlog.is_empty());
+/// sender.send(()).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Backlog(Arc<AtomicUsize>);
+
+impl Backlog {
+    /// Get the number of jobs waiting for a worker
+    pub fn len(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Whether no job is waiting for a worker
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 trait FnBox {
@@ -35,7 +73,7 @@ struct Worker {
 }
 
 impl Worker {
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Message>>>) -> Worker {
+    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Message>>>, backlog: Backlog) -> Worker {
         // create JoinHandle<()> instance
         let thread = thread::spawn(move || {
             loop {
@@ -51,6 +89,7 @@ impl Worker {
                 match message {
                     Message::NewJob(job) => {
                         info!("Worker id: {} received job; executing.", id);
+                        backlog.0.fetch_sub(1, Ordering::Relaxed);
 
                         job.call_box();
                     }
@@ -84,13 +123,24 @@ impl ThreadPool {
 
         let receiver = Arc::new(Mutex::new(receiver));
 
+        let backlog = Backlog::default();
+
         let mut workers = Vec::with_capacity(size);
 
         for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
+            workers.push(Worker::new(id, Arc::clone(&receiver), backlog.clone()));
         }
 
-        ThreadPool { workers, sender }
+        ThreadPool {
+            workers,
+            sender,
+            backlog,
+        }
+    }
+
+    /// Get the number of jobs waiting for a worker, shared with the pool
+    pub fn backlog(&self) -> Backlog {
+        self.backlog.clone()
     }
 
     /// Execute job
@@ -99,6 +149,7 @@ impl ThreadPool {
         F: FnOnce() + Send + 'static,
     {
         let job = Box::new(f);
+        self.backlog.0.fetch_add(1, Ordering::Relaxed);
 
         // unwrap is ok, because threads don't stop executing
         // as long as the pool exists, so failure can't happen
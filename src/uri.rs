@@ -0,0 +1,273 @@
+//! Request-URI handling
+//!
+//! Request-URI = "*" | absoluteURI | abs_path [ "?" query ] | authority
+//!
+//! Only abs_path is used to locate files below the document root.
+
+use std::borrow::Cow;
+use std::{error, fmt};
+
+/// Returned when a Request-URI can not be mapped safely onto the document root
+#[derive(Debug)]
+pub struct InvalidUri(String);
+
+impl fmt::Display for InvalidUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid URI: {}", self.0)
+    }
+}
+
+impl From<&str> for InvalidUri {
+    fn from(uri: &str) -> Self {
+        InvalidUri(uri.to_string())
+    }
+}
+
+impl error::Error for InvalidUri {}
+
+/// Percent-encoded bytes that must never reach the file system:
+/// NUL and the path separators `/` and `\`
+const FORBIDDEN_ENCODINGS: [&str; 3] = ["%00", "%2f", "%5c"];
+
+/// Split a request-target into its path, query and fragment
+///
+/// Clients should not send a fragment, it is split off anyway.
+///
+/// # Examples
+///
+/// ```
+/// use linda::uri::split_target;
+///
+/// assert_eq!(split_target("/index.html?v=2#top"), ("/index.html", Some("v=2"), Some("top")));
+/// assert_eq!(split_target("/index.html"), ("/index.html", None, None));
+/// ```
+pub fn split_target(target: &str) -> (&str, Option<&str>, Option<&str>) {
+    let (rest, fragment) = match target.find('#') {
+        Some(hash) => (&target[..hash], Some(&target[hash + 1..])),
+        None => (target, None),
+    };
+
+    match rest.find('?') {
+        Some(question) => (&rest[..question], Some(&rest[question + 1..]), fragment),
+        None => (rest, None, fragment),
+    }
+}
+
+/// Percent-decode a string, e.g. `my%20file.html` becomes `my file.html`
+///
+/// # Errors
+///
+/// Returns InvalidUri if:
+/// * A `%` is not followed by two hexadecimal digits
+/// * The decoded bytes are not valid UTF-8
+pub fn percent_decode(input: &str) -> Result<Cow<'_, str>, InvalidUri> {
+    if !input.contains('%') {
+        return Ok(Cow::Borrowed(input));
+    }
+
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = match (bytes.get(i + 1), bytes.get(i + 2)) {
+                (Some(&high), Some(&low)) => hex_value(high).zip(hex_value(low)),
+                _ => None,
+            };
+            let (high, low) = byte.ok_or_else(|| InvalidUri::from(input))?;
+
+            decoded.push(high << 4 | low);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded)
+        .map(Cow::Owned)
+        .map_err(|_| InvalidUri::from(input))
+}
+
+/// Percent-decode an abs_path so it can be looked up on the file system
+///
+/// # Errors
+///
+/// Returns InvalidUri if:
+/// * The path contains a percent-encoded NUL byte, `/` or `\`,
+///   decoding those would change how the path is split into segments
+/// * The path can not be decoded, see percent_decode
+pub fn decode_path(path: &str) -> Result<Cow<'_, str>, InvalidUri> {
+    let lowercase = path.to_ascii_lowercase();
+    if FORBIDDEN_ENCODINGS
+        .iter()
+        .any(|encoded| lowercase.contains(encoded))
+    {
+        return Err(InvalidUri::from(path));
+    }
+
+    percent_decode(path)
+}
+
+/// Parse an `application/x-www-form-urlencoded` query into (key, value) pairs
+///
+/// Pairs keep their order and keys may repeat. A key without `=` has an empty value,
+/// `+` stands for a space. Pairs that can not be percent-decoded are kept as sent.
+///
+/// # Examples
+///
+/// ```
+/// use linda::uri::parse_query;
+///
+/// let query = parse_query("tag=a&tag=b+c&q=%C3%A9t%C3%A9&flag");
+///
+/// assert_eq!(query[0], ("tag".into(), "a".into()));
+/// assert_eq!(query[1], ("tag".into(), "b c".into()));
+/// assert_eq!(query[2], ("q".into(), "été".into()));
+/// assert_eq!(query[3], ("flag".into(), "".into()));
+/// ```
+pub fn parse_query(query: &str) -> Vec<(Cow<'_, str>, Cow<'_, str>)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(equals) => (
+                decode_form(&pair[..equals]),
+                decode_form(&pair[equals + 1..]),
+            ),
+            None => (decode_form(pair), Cow::Borrowed("")),
+        })
+        .collect()
+}
+
+/// Decode a form-urlencoded component, leaving it untouched if it is not valid
+fn decode_form(component: &str) -> Cow<'_, str> {
+    if component.contains('+') {
+        let spaced = component.replace('+', " ");
+        return match percent_decode(&spaced) {
+            Ok(decoded) => Cow::Owned(decoded.into_owned()),
+            Err(_) => Cow::Borrowed(component),
+        };
+    }
+
+    percent_decode(component).unwrap_or(Cow::Borrowed(component))
+}
+
+/// Percent-encode an abs_path for use in a header such as Location
+///
+/// `/` and the characters allowed in a path segment are kept as they are.
+///
+/// # Examples
+///
+/// ```
+/// use linda::uri::percent_encode_path;
+///
+/// assert_eq!(percent_encode_path("/my docs/100%/"), "/my%20docs/100%25/");
+/// assert_eq!(percent_encode_path("/été"), "/%C3%A9t%C3%A9");
+/// ```
+pub fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+
+    for byte in path.bytes() {
+        // pchar = unreserved / pct-encoded / sub-delims / ":" / "@"
+        if byte.is_ascii_alphanumeric() || b"/-._~!$&'()*+,;=:@".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    encoded
+}
+
+/// Percent-encode a query as received for use in a header such as Location
+///
+/// The characters allowed in a query and valid percent-encodings are kept
+/// as they are, so that the query keeps its meaning.
+///
+/// # Examples
+///
+/// ```
+/// use linda::uri::percent_encode_query;
+///
+/// assert_eq!(percent_encode_query("a=1&b=%2F/?"), "a=1&b=%2F/?");
+/// assert_eq!(percent_encode_query("q=a b\r\n\0"), "q=a%20b%0D%0A%00");
+/// assert_eq!(percent_encode_query("100%"), "100%25");
+/// ```
+pub fn percent_encode_query(query: &str) -> String {
+    let bytes = query.as_bytes();
+    let mut encoded = String::with_capacity(query.len());
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        let escape = byte == b'%'
+            && bytes.len() > i + 2
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit();
+        // query = *( pchar / "/" / "?" )
+        if escape || byte.is_ascii_alphanumeric() || b"/?-._~!$&'()*+,;=:@".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    encoded
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+/// Normalise a decoded abs_path, resolving `.` and `..` segments
+///
+/// Empty segments are dropped, a trailing slash is kept so that directories
+/// can still be told apart. Percent-encoded sequences are left alone, the path
+/// was decoded already, see decode_path.
+///
+/// # Errors
+///
+/// Returns InvalidUri if the path:
+/// * Does not start with `/`
+/// * Contains a NUL byte or a backslash
+/// * Uses `..` to climb above the root
+///
+/// # Examples
+///
+/// ```
+/// use linda::uri::normalize_path;
+/// # use std::error::Error;
+///
+/// assert_eq!(normalize_path("/docs/./img/../index.html")?, "/docs/index.html");
+/// assert_eq!(normalize_path("//docs//")?, "/docs/");
+///
+/// assert!(normalize_path("/../../etc/passwd").is_err());
+/// assert!(normalize_path("/index.html\0.png").is_err());
+/// assert_eq!(normalize_path("/a%2fb.txt")?, "/a%2fb.txt");
+///
+/// # Ok::<(), Box<dyn Error>>(())
+/// ```
+pub fn normalize_path(path: &str) -> Result<String, InvalidUri> {
+    if !path.starts_with('/') || path.contains(['\0', '\\']) {
+        return Err(InvalidUri::from(path));
+    }
+
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => {
+                segments.pop().ok_or_else(|| InvalidUri::from(path))?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    let directory = path.ends_with('/') || path.ends_with("/.") || path.ends_with("/..");
+    if directory && !segments.is_empty() {
+        normalized.push('/');
+    }
+
+    Ok(normalized)
+}
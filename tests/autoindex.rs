@@ -0,0 +1,246 @@
+//! Directories without an index file can be listed in HTML or JSON
+
+mod common;
+
+use common::Root;
+
+/// A directory without index file
+const FILES: [(&str, &str); 4] = [
+    ("files/.hidden", "hidden"),
+    ("files/b.txt", "twelve bytes"),
+    ("files/z <b>.html", ""),
+    ("files/sub/", ""),
+];
+
+/// Request the listing of /files/ with the given Accept header
+///
+/// Next to FILES, the directory holds links into and out of the document root:
+///
+/// base/
+/// ├── outside/
+/// └── root/files/
+///     ├── outside     -> ../../outside
+///     └── inside      -> sub
+fn list(accept: Option<&str>, autoindex: bool) -> String {
+    let root = Root::with_files(&FILES);
+    root.outside("outside/.keep", "");
+    #[cfg(unix)]
+    root.symlink("files/outside", "outside")
+        .symlink("files/inside", "root/files/sub");
+    let mut config = root.config();
+    config.autoindex_mut(autoindex);
+    let accept = accept.map_or_else(String::new, |accept| format!("Accept: {}\r\n", accept));
+
+    common::exchange_with(config, &common::request("GET", "/files/", &accept))
+}
+
+#[test]
+fn listings_are_off_by_default() {
+    let response = list(None, false);
+
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn html_listing_is_sorted_and_hides_entries() {
+    let response = list(Some("text/html,*/*;q=0.8"), true);
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(
+        response.contains("\r\nContent-Type: text/html; charset=utf-8\r\n"),
+        "{}",
+        response
+    );
+    assert!(response.contains("\r\nVary: Accept\r\n"), "{}", response);
+    assert!(response.contains("<title>Index of /files/</title>"));
+
+    let links: Vec<&str> = response
+        .split("<a href=\"")
+        .skip(1)
+        .map(|link| &link[..link.find('"').unwrap()])
+        .collect();
+    if cfg!(unix) {
+        assert_eq!(
+            links,
+            [
+                "../",
+                "./inside/",
+                "./sub/",
+                "./b.txt",
+                "./z%20%3Cb%3E.html"
+            ]
+        );
+    } else {
+        assert_eq!(links, ["../", "./sub/", "./b.txt", "./z%20%3Cb%3E.html"]);
+    }
+
+    assert!(response.contains(">z &lt;b&gt;.html</a>"), "{}", response);
+    assert!(response.contains("<td>12</td>"), "{}", response);
+    assert!(!response.contains(".hidden"), "{}", response);
+    assert!(!response.contains("outside"), "{}", response);
+}
+
+#[test]
+fn json_listing_is_served_when_preferred() {
+    let response = list(Some("application/json"), true);
+
+    assert!(
+        response.contains("\r\nContent-Type: application/json; charset=utf-8\r\n"),
+        "{}",
+        response
+    );
+
+    let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+    assert!(body.starts_with(r#"{"path":"/files/","entries":[{"name":"#));
+    assert!(
+        body.contains(r#"{"name":"b.txt","href":"./b.txt","type":"file","size":12,"modified":"#)
+    );
+    assert!(
+        body.contains(r#"{"name":"sub","href":"./sub/","type":"directory","size":0,"modified":"#)
+    );
+    assert!(!body.contains(".hidden"), "{}", body);
+    assert!(!body.contains("outside"), "{}", body);
+}
+
+#[test]
+fn names_are_never_links_to_a_scheme() {
+    let root = Root::with_files(&FILES);
+    root.file("files/javascript:alert(document.domain)", "");
+    let mut config = root.config();
+    config.autoindex_mut(true);
+
+    let html = common::exchange_with(config.clone(), &common::request("GET", "/files/", ""));
+    let json = common::exchange_with(
+        config,
+        &common::request("GET", "/files/", "Accept: application/json\r\n"),
+    );
+
+    assert!(
+        html.contains(r#"<a href="./javascript:alert(document.domain)">"#),
+        "{}",
+        html
+    );
+    assert!(!html.contains(r#"href="javascript:"#), "{}", html);
+    assert!(
+        json.contains(r#""href":"./javascript:alert(document.domain)""#),
+        "{}",
+        json
+    );
+}
+
+#[test]
+fn json_is_served_when_named_next_to_wildcards() {
+    // The default of fetch and axios
+    let response = list(Some("application/json, text/plain, */*"), true);
+
+    assert!(
+        response.contains("\r\nContent-Type: application/json; charset=utf-8\r\n"),
+        "{}",
+        response
+    );
+
+    // Browsers name HTML and accept everything else with a lower quality
+    let response = list(
+        Some("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+        true,
+    );
+
+    assert!(
+        response.contains("\r\nContent-Type: text/html; charset=utf-8\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn html_is_served_without_accept_header() {
+    let response = list(None, true);
+
+    assert!(
+        response.contains("\r\nContent-Type: text/html; charset=utf-8\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn hidden_entries_are_not_served_by_default() {
+    let root = Root::with_files(&FILES);
+    root.file(".env", "SECRET=1")
+        .file(".git/config", "[core]")
+        .file("files/.cache/inner.txt", "inner");
+    let mut config = root.config();
+    config.autoindex_mut(true);
+
+    for target in [
+        "/.env",
+        "/.git/",
+        "/.git",
+        "/.git/config",
+        "/files/.hidden",
+        "/files/.cache/inner.txt",
+        "/%2Eenv",
+        "/files/sub/../../.env",
+    ]
+    .iter()
+    {
+        let response = common::exchange_with(config.clone(), &common::request("GET", target, ""));
+
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{}: {}",
+            target,
+            response
+        );
+        assert!(!response.contains("SECRET") && !response.contains("[core]"));
+    }
+}
+
+#[test]
+fn well_known_uris_are_served() {
+    let root = Root::with_files(&FILES);
+    root.file(
+        ".well-known/security.txt",
+        "Contact: mailto:security@example.com",
+    )
+    .file(".well-known/acme-challenge/token", "token.key")
+    .file(".well-known/.secret", "");
+
+    for (target, status) in [
+        ("/.well-known/security.txt", "200"),
+        ("/.well-known/acme-challenge/token", "200"),
+        ("/.well-known/.secret", "404"),
+        ("/files/.well-known/security.txt", "404"),
+    ]
+    .iter()
+    {
+        let response = root.exchange(&common::request("GET", target, ""));
+
+        assert_eq!(
+            common::status(&response),
+            *status,
+            "{}: {}",
+            target,
+            response
+        );
+    }
+}
+
+#[test]
+fn hidden_entries_are_served_when_enabled() {
+    let root = Root::with_files(&FILES);
+    root.file(".env", "SECRET=1");
+    let mut config = root.config();
+    config.autoindex_mut(true).serve_hidden_mut(true);
+
+    let response = common::exchange_with(config.clone(), &common::request("GET", "/.env", ""));
+    assert!(response.ends_with("\r\n\r\nSECRET=1"), "{}", response);
+
+    // Listings still leave them out
+    let response = common::exchange_with(config, &common::request("GET", "/files/", ""));
+    assert!(!response.contains(".hidden"), "{}", response);
+}
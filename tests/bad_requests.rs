@@ -0,0 +1,54 @@
+//! Malformed request heads are answered with an error status before the connection is closed
+
+mod common;
+
+use common::{header, status, Root};
+
+#[test]
+fn malformed_heads_are_bad_requests() {
+    let root = Root::new();
+
+    for request in [
+        &b"GET / HTTP/1.1\r\nNo colon here\r\n\r\n"[..],
+        b"GET / HTTP/1.1\r\nBad name: value\r\n\r\n",
+        b"GET / HTTP/1.1\r\nHost: \xff\xfe\r\n\r\n",
+        b"GET / HTTP/1.1\r\nX-Null: a\0b\r\n\r\n",
+        b"GET /\r\n\r\n",
+        b"BREW / HTTP/1.1\r\n\r\n",
+        b"GET / HTTX/1.1\r\n\r\n",
+    ]
+    .iter()
+    {
+        let response = root.exchange(request);
+
+        assert_eq!(status(&response), "400", "{}", response);
+        assert_eq!(header(&response, "Connection"), Some("close"));
+    }
+}
+
+#[test]
+fn unsupported_versions_are_rejected() {
+    let root = Root::new();
+
+    for version in ["HTTP/1.0", "HTTP/2.0"].iter() {
+        let response = root.exchange(format!("GET / {}\r\n\r\n", version).as_bytes());
+
+        assert!(
+            response.starts_with("HTTP/1.1 505 HTTP Version Not Supported\r\n"),
+            "{}",
+            response
+        );
+        assert_eq!(header(&response, "Connection"), Some("close"));
+    }
+}
+
+#[test]
+fn pipelined_requests_after_a_bad_request_are_dropped() {
+    let responses = Root::new().exchange(
+        b"GET / HTTP/1.1\r\n: no name\r\n\r\n\
+          GET / HTTP/1.1\r\n\r\n",
+    );
+
+    assert!(responses.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert_eq!(responses.matches("HTTP/1.1 ").count(), 1, "{}", responses);
+}
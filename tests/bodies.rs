@@ -0,0 +1,271 @@
+//! Request bodies are read according to Content-Length or the chunked transfer-coding,
+//! within the configured maximum
+
+mod common;
+
+use common::{header, status, Root};
+use linda::config::Config;
+use linda::request::{parse_request, RequestBody};
+use std::cell::Cell;
+use std::io::{self, Read};
+
+/// Send a POST request with extra header lines and a body, return the response
+fn post_with(config: Config, headers: &str, body: &str) -> String {
+    let mut request = common::request("POST", "/", headers);
+    request.extend_from_slice(body.as_bytes());
+    common::exchange_with(config, &request)
+}
+
+fn post(root: &Root, headers: &str, body: &str) -> String {
+    post_with(root.config(), headers, body)
+}
+
+#[test]
+fn bodies_of_content_length_are_read() {
+    let response = post(&Root::new(), "Content-Length: 5\r\n", "hello");
+
+    // Nothing handles POST, but the body was read in full
+    assert_eq!(status(&response), "501", "{}", response);
+}
+
+#[test]
+fn missing_content_length_is_length_required() {
+    let root = Root::new();
+
+    for method in ["POST", "PUT"].iter() {
+        let response = root.exchange(&common::request(method, "/", ""));
+
+        assert!(
+            response.starts_with("HTTP/1.1 411 Length Required\r\n"),
+            "{}",
+            response
+        );
+        assert_eq!(header(&response, "Connection"), Some("close"));
+    }
+}
+
+#[test]
+fn bodies_over_the_maximum_are_too_large() {
+    let root = Root::new();
+    let mut config = root.config();
+    config.max_body_size_mut(4);
+
+    let response = post_with(config, "Content-Length: 5\r\n", "hello");
+
+    assert!(
+        response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"),
+        "{}",
+        response
+    );
+    assert_eq!(header(&response, "Connection"), Some("close"));
+}
+
+#[test]
+fn invalid_content_lengths_are_bad_requests() {
+    let root = Root::new();
+
+    for headers in [
+        "Content-Length: 5, 6\r\n",
+        "Content-Length: 5\r\nContent-Length: 6\r\n",
+        "Content-Length: 5, 5\r\n",
+        "Content-Length: 5\r\nContent-Length: 5\r\n",
+        "Content-Length: -5\r\n",
+        "Content-Length: five\r\n",
+        "Content-Length: 99999999999999999999999\r\n",
+    ]
+    .iter()
+    {
+        let response = post(&root, headers, "hello");
+
+        assert_eq!(status(&response), "400", "{}: {}", headers, response);
+        assert_eq!(header(&response, "Connection"), Some("close"));
+    }
+}
+
+/// Send a chunked POST request with its encoded body, return the response
+fn post_chunked(config: Config, encoded: &str) -> String {
+    post_with(config, "Transfer-Encoding: chunked\r\n", encoded)
+}
+
+#[test]
+fn chunked_bodies_with_extensions_and_trailers_are_read() {
+    let root = Root::new();
+
+    for encoded in [
+        "5\r\nhello\r\n0\r\n\r\n",
+        "5;name=value\r\nhello\r\n0 ; last\r\n\r\n",
+        "5;name=\"a;b\"\r\nhello\r\n0;quoted=\"\\\"x\\\"\"\r\n\r\n",
+        "5\r\nhello\r\n0\r\nExpires: never\r\nChecksum: abc\r\n\r\n",
+    ]
+    .iter()
+    {
+        let response = post_chunked(root.config(), encoded);
+
+        assert_eq!(status(&response), "501", "{:?}: {}", encoded, response);
+    }
+}
+
+#[test]
+fn trailers_do_not_leak_into_the_next_request() {
+    let root = Root::new();
+    root.file("notes.txt", "notes");
+
+    let responses = root.exchange(
+        b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+          5\r\nhello\r\n0\r\nExpires: never\r\n\r\n\
+          GET /notes.txt HTTP/1.1\r\nConnection: close\r\n\r\n",
+    );
+
+    assert!(responses.starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+    assert!(responses.contains("HTTP/1.1 200 OK\r\n"), "{}", responses);
+    assert!(responses.ends_with("\r\n\r\nnotes"), "{}", responses);
+}
+
+#[test]
+fn malformed_chunked_bodies_are_bad_requests() {
+    let root = Root::new();
+
+    for encoded in [
+        // Invalid chunk sizes
+        "g\r\nhello\r\n0\r\n\r\n",
+        "\r\nhello\r\n0\r\n\r\n",
+        "-5\r\nhello\r\n0\r\n\r\n",
+        " 5\r\nhello\r\n0\r\n\r\n",
+        // Overflowing chunk size
+        "10000000000000000\r\nhello\r\n0\r\n\r\n",
+        // Missing CRLF after chunk-data
+        "5\r\nhelloGET / HTTP/1.1\r\n0\r\n\r\n",
+        // Invalid chunk extensions
+        "5;\r\nhello\r\n0\r\n\r\n",
+        "5;name=\"a;b\r\nhello\r\n0\r\n\r\n",
+        "5;name=\r\nhello\r\n0\r\n\r\n",
+        // Invalid trailer field
+        "5\r\nhello\r\n0\r\nno colon\r\n\r\n",
+        "5\r\nhello\r\n0\r\nX-Null: a\0b\r\n\r\n",
+    ]
+    .iter()
+    {
+        let response = post_chunked(root.config(), encoded);
+
+        assert_eq!(status(&response), "400", "{:?}: {}", encoded, response);
+        assert_eq!(header(&response, "Connection"), Some("close"));
+    }
+}
+
+#[test]
+fn chunked_bodies_over_the_maximum_are_too_large() {
+    let root = Root::new();
+    let mut config = root.config();
+    config.max_body_size_mut(8);
+
+    let response = post_chunked(config, "5\r\nhello\r\n5\r\nworld\r\n0\r\n\r\n");
+
+    assert!(
+        response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"),
+        "{}",
+        response
+    );
+    assert_eq!(header(&response, "Connection"), Some("close"));
+}
+
+#[test]
+fn chunked_bodies_are_read_without_a_maximum() {
+    let root = Root::new();
+    let mut config = root.config();
+    config.max_body_size_mut(usize::MAX);
+
+    let response = post_chunked(config, "5\r\nhello\r\n0\r\n\r\n");
+
+    assert_eq!(status(&response), "501", "{}", response);
+}
+
+#[test]
+fn transfer_encoding_with_content_length_is_a_bad_request() {
+    let root = Root::new();
+
+    let response = post_with(
+        root.config(),
+        "Transfer-Encoding: chunked\r\nContent-Length: 10\r\n",
+        "5\r\nhello\r\n0\r\n\r\n",
+    );
+
+    assert_eq!(status(&response), "400", "{}", response);
+    assert_eq!(header(&response, "Connection"), Some("close"));
+}
+
+/// Reader counting the bytes read from it
+struct Counting<'a> {
+    inner: &'a [u8],
+    read: &'a Cell<usize>,
+}
+
+impl<'a> Read for Counting<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read.set(self.read.get() + read);
+        Ok(read)
+    }
+}
+
+#[test]
+fn streamed_bodies_are_read_as_they_are_consumed() {
+    let body: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+    let mut chunked: Vec<u8> = body
+        .chunks(1000)
+        .flat_map(|chunk| {
+            let mut encoded = format!("{:x}\r\n", chunk.len()).into_bytes();
+            encoded.extend_from_slice(chunk);
+            encoded.extend_from_slice(b"\r\n");
+            encoded
+        })
+        .collect();
+    chunked.extend_from_slice(b"0\r\n\r\n");
+
+    for (headers, encoded) in [
+        (format!("Content-Length: {}", body.len()), body.clone()),
+        ("Transfer-Encoding: chunked".to_string(), chunked),
+    ]
+    .iter()
+    {
+        let read = Cell::new(0);
+        let head = format!("POST /upload HTTP/1.1\r\n{}\r\n\r\n", headers);
+        let mut request = parse_request(&head).unwrap();
+        let stream = Counting {
+            inner: encoded,
+            read: &read,
+        };
+        let stream = RequestBody::new(&request, b"", stream, body.len()).unwrap();
+        request.body_stream_mut(stream);
+
+        let mut start = vec![0; 4096];
+        request.body_reader().read_exact(&mut start).unwrap();
+        // No more than a buffer of the chunked decoder ahead
+        assert!(read.get() < 16 * 1024, "{}: {}", headers, read.get());
+
+        request.body_reader().read_to_end(&mut start).unwrap();
+        assert!(start == body, "{}", headers);
+        assert_eq!(read.get(), encoded.len(), "{}", headers);
+        assert!(request.finish_body().unwrap().is_empty());
+        assert!(request.body().is_empty());
+    }
+}
+
+#[test]
+fn unread_bodies_are_skipped_before_the_next_request() {
+    let root = Root::new();
+    root.file("notes.txt", "notes");
+    let body = "x".repeat(100 * 1024);
+
+    let responses = root.exchange(
+        format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}\
+             GET /notes.txt HTTP/1.1\r\nConnection: close\r\n\r\n",
+            body.len(),
+            body
+        )
+        .as_bytes(),
+    );
+
+    assert!(responses.starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+    assert!(responses.ends_with("\r\n\r\nnotes"), "{}", responses);
+}
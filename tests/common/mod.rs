@@ -0,0 +1,241 @@
+//! Helpers shared by the integration tests
+
+#![allow(dead_code)]
+
+use linda::config::Config;
+use linda::handle_connection;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+use std::{env, fs, thread};
+
+/// A document root in the temporary directory, removed again when dropped
+///
+/// Files outside of the document root, e.g. targets of traversal payloads,
+/// go in its base directory:
+///
+/// base/       (removed on drop)
+/// └── root/   (served)
+pub struct Root {
+    base: PathBuf,
+}
+
+impl Root {
+    /// Create an empty document root, unique to the calling test
+    pub fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let base = env::temp_dir().join(format!(
+            "linda-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(base.join("root")).unwrap();
+
+        Root { base }
+    }
+
+    /// Create a document root holding `files`, given as (path, contents) pairs
+    ///
+    /// Paths ending in `/` are created as empty directories.
+    pub fn with_files<C: AsRef<[u8]>>(files: &[(&str, C)]) -> Self {
+        let root = Root::new();
+        for (path, contents) in files {
+            if path.ends_with('/') {
+                root.dir(path);
+            } else {
+                root.file(path, contents);
+            }
+        }
+        root
+    }
+
+    /// Get the served directory
+    pub fn path(&self) -> PathBuf {
+        self.base.join("root")
+    }
+
+    /// Get the directory holding the document root
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Write a file below the document root, creating its parent directories
+    pub fn file<C: AsRef<[u8]>>(&self, path: &str, contents: C) -> &Self {
+        let file = self.path().join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, contents).unwrap();
+        self
+    }
+
+    /// Create a directory below the document root
+    pub fn dir(&self, path: &str) -> &Self {
+        fs::create_dir_all(self.path().join(path)).unwrap();
+        self
+    }
+
+    /// Write a file in the base directory, outside of the document root
+    pub fn outside<C: AsRef<[u8]>>(&self, path: &str, contents: C) -> &Self {
+        let file = self.base.join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, contents).unwrap();
+        self
+    }
+
+    /// Create a symbolic link below the document root to `target`,
+    /// relative to the base directory
+    #[cfg(unix)]
+    pub fn symlink(&self, path: &str, target: &str) -> &Self {
+        std::os::unix::fs::symlink(self.base.join(target), self.path().join(path)).unwrap();
+        self
+    }
+
+    /// Set when a file below the document root was last modified
+    pub fn modified(&self, path: &str, time: SystemTime) -> &Self {
+        fs::File::options()
+            .write(true)
+            .open(self.path().join(path))
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+        self
+    }
+
+    /// Get the default configuration serving this root with `Server: Linda`
+    pub fn config(&self) -> Config {
+        let mut config = Config::new();
+        config
+            .root_mut(self.path().to_str().unwrap().to_string())
+            .server_mut(Some("Linda".to_string()));
+        config
+    }
+
+    /// Like exchange_with, using the configuration of this root
+    pub fn exchange(&self, requests: &[u8]) -> String {
+        exchange_with(self.config(), requests)
+    }
+
+    /// Like exchange_bytes, using the configuration of this root
+    pub fn exchange_bytes(&self, requests: &[u8]) -> Vec<u8> {
+        exchange_bytes(self.config(), requests)
+    }
+}
+
+impl Drop for Root {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.base);
+    }
+}
+
+/// Serve one connection with `config` on a new listener, returning its address
+///
+/// The returned handle panics when joined if handle_connection failed.
+pub fn serve(config: Config) -> (std::net::SocketAddr, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &config).unwrap();
+    });
+
+    (address, server)
+}
+
+/// Send `requests` in one write to a connection served with `config`,
+/// and return the raw bytes received until the server closes it.
+pub fn exchange_bytes(config: Config, requests: &[u8]) -> Vec<u8> {
+    let (address, server) = serve(config);
+
+    let mut client = TcpStream::connect(address).unwrap();
+    client.write_all(requests).unwrap();
+
+    let mut responses = Vec::new();
+    client.read_to_end(&mut responses).unwrap();
+    // Close like clients do once the server closed, rather than leaving it to linger
+    drop(client);
+    server.join().unwrap();
+
+    responses
+}
+
+/// Like exchange_bytes, returning the responses as text.
+///
+/// The value of every Date header is replaced by `<date>`
+pub fn exchange_with(config: Config, requests: &[u8]) -> String {
+    String::from_utf8_lossy(&exchange_bytes(config, requests))
+        .split("\r\n")
+        .map(|line| {
+            if line.starts_with("Date: ") {
+                // e.g. Sun, 06 Nov 1994 08:49:37 GMT
+                assert!(line.len() == 35 && line.ends_with(" GMT"), "{}", line);
+                "Date: <date>"
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Like exchange_with, using the default configuration with `Server: Linda`
+pub fn exchange(requests: &[u8]) -> String {
+    let mut config = Config::new();
+    config.server_mut(Some("Linda".to_string()));
+
+    exchange_with(config, requests)
+}
+
+/// Format a request for `target` with extra header lines, closing the connection
+pub fn request(method: &str, target: &str, headers: &str) -> Vec<u8> {
+    format!(
+        "{} {} HTTP/1.1\r\n{}Connection: close\r\n\r\n",
+        method, target, headers
+    )
+    .into_bytes()
+}
+
+/// Send a request for `target` with extra header lines to a connection served
+/// with `config`, and return the head and body of the response
+pub fn request_with(
+    config: Config,
+    method: &str,
+    target: &str,
+    headers: &str,
+) -> (String, Vec<u8>) {
+    let response = exchange_bytes(config, &request(method, target, headers));
+    let (head, body) = split_head(&response);
+    (head, body.to_vec())
+}
+
+/// Get the value of the first `name` header in `response`
+pub fn header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+    let head = &response[..response
+        .find("\r\n\r\n")
+        .map_or(response.len(), |end| end + 2)];
+    let prefix = format!("\r\n{}: ", name);
+    let start = head.find(&prefix)? + prefix.len();
+    Some(&head[start..start + head[start..].find("\r\n")?])
+}
+
+/// Get the status code of `response`
+pub fn status(response: &str) -> &str {
+    &response[9..12]
+}
+
+/// Get the body of the first response in `response`
+pub fn body(response: &str) -> &str {
+    &response[response.find("\r\n\r\n").unwrap() + 4..]
+}
+
+/// Split the first response off raw `responses` into its head, up to the last CRLF,
+/// and everything after the empty line
+pub fn split_head(responses: &[u8]) -> (String, &[u8]) {
+    let end = responses.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    (
+        String::from_utf8(responses[..end + 2].to_vec()).unwrap(),
+        &responses[end + 4..],
+    )
+}
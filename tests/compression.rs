@@ -0,0 +1,227 @@
+//! Compressible bodies are sent gzip or deflate encoded to clients accepting them
+
+mod common;
+
+use common::{header, request_with, Root};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use linda::config::Config;
+use std::io::Read;
+
+/// large.txt and image.png are large enough to be compressed, small.txt is not
+fn files() -> [(&'static str, Vec<u8>); 3] {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend_from_slice(&large());
+
+    [
+        ("large.txt", large()),
+        ("small.txt", b"small".to_vec()),
+        ("image.png", png),
+    ]
+}
+
+fn large() -> Vec<u8> {
+    "All work and no play makes Jack a dull boy.\n"
+        .repeat(100)
+        .into_bytes()
+}
+
+fn get_with(config: Config, target: &str, headers: &str) -> (String, Vec<u8>) {
+    request_with(config, "GET", target, headers)
+}
+
+fn get(root: &Root, target: &str, headers: &str) -> (String, Vec<u8>) {
+    get_with(root.config(), target, headers)
+}
+
+#[test]
+fn gzip_is_preferred() {
+    let root = Root::with_files(&files());
+    let (head, body) = get(
+        &root,
+        "/large.txt",
+        "Accept-Encoding: deflate, gzip, br\r\n",
+    );
+
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+    assert_eq!(header(&head, "Content-Encoding"), Some("gzip"));
+    assert_eq!(header(&head, "Vary"), Some("Accept-Encoding"));
+    assert_eq!(
+        header(&head, "Content-Length"),
+        Some(body.len().to_string().as_str())
+    );
+    assert!(body.len() < large().len());
+    // Ranges of the compressed bytes are not supported
+    assert_eq!(header(&head, "Accept-Ranges"), None);
+
+    let mut decoded = Vec::new();
+    GzDecoder::new(&body[..]).read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, large());
+}
+
+#[test]
+fn head_requests_are_negotiated_like_get() {
+    let root = Root::with_files(&files());
+
+    for accept_encoding in ["gzip", "deflate", "identity"].iter() {
+        let headers = format!("Accept-Encoding: {}\r\n", accept_encoding);
+        let (get_head, _) = request_with(root.config(), "GET", "/large.txt", &headers);
+        let (head_head, body) = request_with(root.config(), "HEAD", "/large.txt", &headers);
+
+        assert!(body.is_empty());
+        // The body is not compressed just to announce its length
+        let length = match *accept_encoding {
+            "identity" => header(&get_head, "Content-Length"),
+            _ => None,
+        };
+        assert_eq!(header(&head_head, "Content-Length"), length);
+        for name in [
+            "Content-Type",
+            "Content-Encoding",
+            "Vary",
+            "ETag",
+            "Accept-Ranges",
+        ]
+        .iter()
+        {
+            assert_eq!(
+                header(&head_head, name),
+                header(&get_head, name),
+                "{}: {}",
+                accept_encoding,
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn quality_values_are_respected() {
+    let root = Root::with_files(&files());
+    let (head, body) = get(
+        &root,
+        "/large.txt",
+        "Accept-Encoding: gzip;q=0.5, deflate\r\n",
+    );
+
+    assert_eq!(header(&head, "Content-Encoding"), Some("deflate"));
+    let mut decoded = Vec::new();
+    ZlibDecoder::new(&body[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, large());
+
+    for accept_encoding in ["gzip;q=0", "br", "*;q=0.5, identity", "identity"].iter() {
+        let (head, body) = get(
+            &root,
+            "/large.txt",
+            &format!("Accept-Encoding: {}\r\n", accept_encoding),
+        );
+
+        assert_eq!(
+            header(&head, "Content-Encoding"),
+            None,
+            "{}",
+            accept_encoding
+        );
+        assert_eq!(header(&head, "Vary"), Some("Accept-Encoding"));
+        assert_eq!(body, large(), "{}", accept_encoding);
+    }
+}
+
+#[test]
+fn identity_without_accept_encoding() {
+    let root = Root::with_files(&files());
+    let (head, body) = get(&root, "/large.txt", "");
+
+    assert_eq!(header(&head, "Content-Encoding"), None);
+    assert_eq!(header(&head, "Vary"), Some("Accept-Encoding"));
+    assert_eq!(body, large());
+}
+
+#[test]
+fn compressed_etags_are_weak() {
+    let root = Root::with_files(&files());
+    let (head, _) = get(&root, "/large.txt", "");
+    let etag = header(&head, "ETag").unwrap().trim_start_matches("W/");
+
+    let (head, _) = get(&root, "/large.txt", "Accept-Encoding: gzip\r\n");
+    assert_eq!(
+        header(&head, "ETag"),
+        Some(format!("W/{}", etag).as_str()),
+        "{}",
+        head
+    );
+}
+
+#[test]
+fn not_modified_responses_agree_with_the_representation() {
+    let root = Root::with_files(&files());
+
+    for accept_encoding in ["gzip", "identity"].iter() {
+        let headers = format!("Accept-Encoding: {}\r\n", accept_encoding);
+        let (full, _) = get(&root, "/large.txt", &headers);
+        let etag = header(&full, "ETag").unwrap();
+
+        let (not_modified, _) = get(
+            &root,
+            "/large.txt",
+            &format!("{}If-None-Match: {}\r\n", headers, etag),
+        );
+
+        assert!(
+            not_modified.starts_with("HTTP/1.1 304 Not Modified\r\n"),
+            "{}",
+            not_modified
+        );
+        assert_eq!(header(&not_modified, "ETag"), Some(etag));
+        assert_eq!(header(&not_modified, "Vary"), Some("Accept-Encoding"));
+    }
+}
+
+#[test]
+fn small_and_compressed_files_are_sent_as_is() {
+    let root = Root::with_files(&files());
+    for target in ["/small.txt", "/image.png"].iter() {
+        let (head, _) = get(&root, target, "Accept-Encoding: gzip\r\n");
+
+        assert_eq!(header(&head, "Content-Encoding"), None, "{}", target);
+        assert_eq!(header(&head, "Vary"), None, "{}", target);
+    }
+
+    let mut config = root.config();
+    config.compression_min_size_mut(1);
+    let (head, _) = get_with(config, "/small.txt", "Accept-Encoding: gzip\r\n");
+    // Compressing 5 bytes only makes them larger
+    assert_eq!(header(&head, "Content-Encoding"), None);
+    assert_eq!(header(&head, "Vary"), Some("Accept-Encoding"));
+}
+
+#[test]
+fn compression_can_be_disabled() {
+    let root = Root::with_files(&files());
+    let mut config = root.config();
+    config.compression_mut(false);
+    let (head, body) = get_with(config, "/large.txt", "Accept-Encoding: gzip\r\n");
+
+    assert_eq!(header(&head, "Content-Encoding"), None);
+    assert_eq!(header(&head, "Vary"), None);
+    assert_eq!(body, large());
+}
+
+#[test]
+fn partial_content_is_not_compressed() {
+    let root = Root::with_files(&files());
+    let (head, body) = get(
+        &root,
+        "/large.txt",
+        "Accept-Encoding: gzip\r\nRange: bytes=0-2\r\n",
+    );
+
+    assert!(
+        head.starts_with("HTTP/1.1 206 Partial Content\r\n"),
+        "{}",
+        head
+    );
+    assert_eq!(header(&head, "Content-Encoding"), None);
+    assert_eq!(body, b"All");
+}
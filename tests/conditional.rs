@@ -0,0 +1,213 @@
+//! Conditional requests are answered with 304 Not Modified or 412 Precondition Failed
+
+mod common;
+
+use common::{header, status, Root};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Modification time of page.html
+const MODIFIED: &str = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+/// Create a document root with page.html, last modified at MODIFIED
+fn page_root() -> Root {
+    let root = Root::with_files(&[("page.html", "page")]);
+    root.modified("page.html", UNIX_EPOCH + Duration::from_secs(784_111_777));
+    root
+}
+
+/// Send a request with extra header lines and return the response
+fn request(root: &Root, method: &str, target: &str, headers: &str) -> String {
+    root.exchange(&common::request(method, target, headers))
+}
+
+fn get(root: &Root, headers: &str) -> String {
+    request(root, "GET", "/page.html", headers)
+}
+
+fn etag(root: &Root) -> String {
+    header(&get(root, ""), "ETag").unwrap().to_string()
+}
+
+#[test]
+fn files_carry_validators() {
+    let root = page_root();
+    let response = get(&root, "");
+    let etag = header(&response, "ETag").unwrap();
+
+    assert_eq!(status(&response), "200");
+    assert!(etag.starts_with('"') && etag.ends_with('"'), "{}", etag);
+    assert_eq!(header(&response, "Last-Modified"), Some(MODIFIED));
+}
+
+#[test]
+fn recently_modified_files_get_weak_etags() {
+    let root = page_root();
+    root.file("fresh.txt", "fresh");
+
+    let response = request(&root, "GET", "/fresh.txt", "");
+    let etag = header(&response, "ETag").unwrap();
+
+    assert!(etag.starts_with("W/\""), "{}", etag);
+}
+
+#[test]
+fn matching_if_none_match_is_not_modified() {
+    let root = page_root();
+    let etag = etag(&root);
+
+    for if_none_match in [
+        etag.clone(),
+        format!("W/{}", etag),
+        format!("\"other\", {}", etag),
+        String::from("*"),
+    ]
+    .iter()
+    {
+        let response = get(&root, &format!("If-None-Match: {}\r\n", if_none_match));
+
+        assert!(
+            response.starts_with("HTTP/1.1 304 Not Modified\r\n"),
+            "{}: {}",
+            if_none_match,
+            response
+        );
+        assert_eq!(header(&response, "ETag"), Some(etag.as_str()));
+        assert_eq!(header(&response, "Last-Modified"), Some(MODIFIED));
+        assert_eq!(header(&response, "Content-Length"), None);
+        assert!(response.ends_with("\r\n\r\n"), "{}", response);
+    }
+
+    let response = get(&root, "If-None-Match: \"other\"\r\n");
+    assert_eq!(status(&response), "200");
+}
+
+#[test]
+fn if_modified_since_compares_seconds() {
+    let root = page_root();
+    for (since, expected) in [
+        (MODIFIED, "304"),
+        ("Mon, 07 Nov 1994 08:49:37 GMT", "304"),
+        ("Sunday, 06-Nov-94 08:49:37 GMT", "304"),
+        ("Sun, 06 Nov 1994 08:49:36 GMT", "200"),
+        ("yesterday", "200"),
+    ]
+    .iter()
+    {
+        let response = get(&root, &format!("If-Modified-Since: {}\r\n", since));
+
+        assert_eq!(status(&response), *expected, "{}: {}", since, response);
+    }
+}
+
+/// Dates whose year overflows the number of seconds since 1970 or a SystemTime
+const FAR_FUTURE: [&str; 3] = [
+    "Sun, 06 Nov 18446744073709551615 08:49:37 GMT",
+    "Sun, 06 Nov 584554051223 08:49:37 GMT",
+    "Sun Nov  6 08:49:37 99999999999999999",
+];
+
+#[test]
+fn out_of_range_dates_are_ignored() {
+    let root = page_root();
+
+    for date in FAR_FUTURE.iter() {
+        for (headers, expected) in [
+            (format!("If-Modified-Since: {}\r\n", date), "200"),
+            (format!("If-Unmodified-Since: {}\r\n", date), "200"),
+            (format!("Range: bytes=0-1\r\nIf-Range: {}\r\n", date), "200"),
+        ]
+        .iter()
+        {
+            let response = get(&root, headers);
+
+            assert_eq!(status(&response), *expected, "{}: {}", headers, response);
+            assert!(response.ends_with("\r\n\r\npage"), "{}", response);
+        }
+    }
+}
+
+#[test]
+fn if_none_match_takes_precedence_over_if_modified_since() {
+    let root = page_root();
+    let response = get(
+        &root,
+        &format!(
+            "If-None-Match: \"other\"\r\nIf-Modified-Since: {}\r\n",
+            MODIFIED
+        ),
+    );
+
+    assert_eq!(status(&response), "200", "{}", response);
+}
+
+#[test]
+fn failing_if_match_is_precondition_failed() {
+    let root = page_root();
+    let etag = etag(&root);
+
+    for (if_match, expected) in [
+        (etag.clone(), "200"),
+        (String::from("*"), "200"),
+        (format!("\"other\", {}", etag), "200"),
+        (String::from("\"other\""), "412"),
+        (format!("W/{}", etag), "412"),
+    ]
+    .iter()
+    {
+        let response = get(&root, &format!("If-Match: {}\r\n", if_match));
+
+        assert_eq!(status(&response), *expected, "{}: {}", if_match, response);
+    }
+}
+
+#[test]
+fn if_unmodified_since_fails_for_later_changes() {
+    let root = page_root();
+    let response = get(
+        &root,
+        "If-Unmodified-Since: Sat, 05 Nov 1994 08:49:37 GMT\r\n",
+    );
+    assert_eq!(status(&response), "412", "{}", response);
+
+    let response = get(&root, &format!("If-Unmodified-Since: {}\r\n", MODIFIED));
+    assert_eq!(status(&response), "200", "{}", response);
+
+    // If-Match is evaluated instead when both are present
+    let response = get(
+        &root,
+        &format!(
+            "If-Match: {}\r\nIf-Unmodified-Since: Sat, 05 Nov 1994 08:49:37 GMT\r\n",
+            etag(&root)
+        ),
+    );
+    assert_eq!(status(&response), "200", "{}", response);
+}
+
+#[test]
+fn if_range_accepts_strong_etags() {
+    let root = page_root();
+    let response = get(
+        &root,
+        &format!("Range: bytes=0-1\r\nIf-Range: {}\r\n", etag(&root)),
+    );
+
+    assert_eq!(status(&response), "206", "{}", response);
+    assert!(response.ends_with("\r\n\r\npa"), "{}", response);
+}
+
+#[test]
+fn not_modified_responses_keep_the_connection_in_sync() {
+    let root = page_root();
+    let responses = root.exchange(
+        format!(
+            "GET /page.html HTTP/1.1\r\nIf-Modified-Since: {}\r\n\r\n\
+             GET /page.html HTTP/1.1\r\nConnection: close\r\n\r\n",
+            MODIFIED
+        )
+        .as_bytes(),
+    );
+
+    assert!(responses.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+    assert_eq!(responses.matches("HTTP/1.1 200 OK\r\n").count(), 1);
+    assert!(responses.ends_with("\r\n\r\npage"), "{}", responses);
+}
@@ -0,0 +1,180 @@
+//! Every existing file is served, with a Content-Type even for unknown extensions
+
+mod common;
+
+use common::Root;
+use linda::config::Config;
+use linda::content::ContentType;
+
+/// Files with known, unknown and missing extensions
+const FILES: [(&str, &[u8]); 14] = [
+    ("style.css", b"body {}"),
+    ("LOGO.PNG", b"png"),
+    ("app.js", b"app"),
+    ("app.wasm", b"wasm"),
+    ("app.unknown", b"binary"),
+    ("README", b"readme"),
+    ("v1.2/LICENSE", b"license"),
+    ("uploads/3f2a9c", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+    ("uploads/8b01e4", b"\xff\xd8\xff\xe0\0\x10JFIF"),
+    ("uploads/c7d210", b"GIF89a\x01\0\x01\0"),
+    ("uploads/05aa7e", b"%PDF-1.7\n"),
+    ("uploads/e4410b", b"\n<!DOCTYPE html>\n<title>Hi</title>"),
+    ("uploads/91cc3d", "Grüße aus Wien\n".as_bytes()),
+    ("uploads/d00f5e", b"\0\x01\x02\x03"),
+];
+
+/// Request `target` with `config` and return the response
+fn get_with(config: Config, target: &str) -> String {
+    common::exchange_with(config, &common::request("GET", target, ""))
+}
+
+#[test]
+fn known_extensions_keep_their_type() {
+    let root = Root::with_files(&FILES);
+    assert!(get_with(root.config(), "/style.css")
+        .contains("\r\nContent-Type: text/css; charset=utf-8\r\n"));
+    assert!(get_with(root.config(), "/LOGO.PNG").contains("\r\nContent-Type: image/png\r\n"));
+    assert!(get_with(root.config(), "/app.js")
+        .contains("\r\nContent-Type: text/javascript; charset=utf-8\r\n"));
+    assert!(get_with(root.config(), "/app.wasm").contains("\r\nContent-Type: application/wasm\r\n"));
+}
+
+#[test]
+fn loaded_and_overridden_types_take_precedence() {
+    let root = Root::with_files(&FILES);
+    let mut config = root.config();
+    config
+        .mime_types_mut()
+        .load(&b"application/x-unknown unknown\napplication/javascript js\n"[..])
+        .unwrap();
+    config
+        .mime_types_mut()
+        .insert_list("js=application/x-javascript")
+        .unwrap();
+
+    let response = get_with(config.clone(), "/app.unknown");
+    assert!(
+        response.contains("\r\nContent-Type: application/x-unknown\r\n"),
+        "{}",
+        response
+    );
+
+    let response = get_with(config, "/app.js");
+    assert!(
+        response.contains("\r\nContent-Type: application/x-javascript\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn unknown_and_missing_extensions_fall_back_to_octet_stream() {
+    let root = Root::with_files(&FILES);
+    for (target, body) in [
+        ("/app.unknown", "binary"),
+        ("/README", "readme"),
+        ("/v1.2/LICENSE", "license"),
+    ]
+    .iter()
+    {
+        let response = get_with(root.config(), target);
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(
+            response.contains("\r\nContent-Type: application/octet-stream\r\n"),
+            "{}",
+            response
+        );
+        assert!(response.ends_with(body), "{}", response);
+    }
+}
+
+#[test]
+fn fallback_type_is_configurable() {
+    let root = Root::with_files(&FILES);
+    let mut config = root.config();
+    config.default_content_type_mut(ContentType::TEXT);
+
+    let response = get_with(config, "/README");
+
+    assert!(
+        response.contains("\r\nContent-Type: text/plain; charset=utf-8\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn default_charset_is_configurable() {
+    let root = Root::with_files(&FILES);
+    let mut config = root.config();
+    config.default_charset_mut(Some("iso-8859-1".to_string()));
+    config
+        .mime_types_mut()
+        .insert_list("unknown=text/plain; charset=us-ascii")
+        .unwrap();
+
+    let response = get_with(config.clone(), "/style.css");
+    assert!(
+        response.contains("\r\nContent-Type: text/css; charset=iso-8859-1\r\n"),
+        "{}",
+        response
+    );
+
+    let response = get_with(config.clone(), "/app.unknown");
+    assert!(
+        response.contains("\r\nContent-Type: text/plain; charset=us-ascii\r\n"),
+        "{}",
+        response
+    );
+
+    config.default_charset_mut(None);
+    let response = get_with(config, "/style.css");
+    assert!(
+        response.contains("\r\nContent-Type: text/css\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn sniffing_is_opt_in() {
+    let root = Root::with_files(&FILES);
+    let response = get_with(root.config(), "/uploads/3f2a9c");
+
+    assert!(
+        response.contains("\r\nContent-Type: application/octet-stream\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn sniffing_recognises_magic_numbers_html_and_text() {
+    let root = Root::with_files(&FILES);
+    let mut config = root.config();
+    config.sniff_content_type_mut(true);
+
+    for (target, content_type) in [
+        ("/uploads/3f2a9c", "image/png"),
+        ("/uploads/8b01e4", "image/jpeg"),
+        ("/uploads/c7d210", "image/gif"),
+        ("/uploads/05aa7e", "application/pdf"),
+        ("/uploads/e4410b", "text/html; charset=utf-8"),
+        ("/uploads/91cc3d", "text/plain; charset=utf-8"),
+        ("/uploads/d00f5e", "application/octet-stream"),
+        ("/style.css", "text/css; charset=utf-8"),
+    ]
+    .iter()
+    {
+        let response = get_with(config.clone(), target);
+
+        assert!(
+            response.contains(&format!("\r\nContent-Type: {}\r\n", content_type)),
+            "{}: {}",
+            target,
+            response
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Directories are served through their index file, at a URI ending with a slash
+
+mod common;
+
+use common::Root;
+use linda::config::Config;
+
+/// Directories holding different index files
+///
+/// root/
+/// ├── index.html
+/// ├── docs/index.htm
+/// ├── both/{index.html, index.htm}
+/// ├── my docs/index.html
+/// └── empty/
+const FILES: [(&str, &str); 6] = [
+    ("index.html", "root"),
+    ("docs/index.htm", "docs"),
+    ("both/index.html", "both html"),
+    ("both/index.htm", "both htm"),
+    ("my docs/index.html", "my docs"),
+    ("empty/", ""),
+];
+
+/// Request `target` with `config` and return the response
+fn get_with(config: Config, target: &str) -> String {
+    common::exchange_with(config, &common::request("GET", target, ""))
+}
+
+#[test]
+fn directories_are_served_through_the_first_index_file() {
+    let root = Root::with_files(&FILES);
+    for (target, body) in [
+        ("/", "root"),
+        ("/docs/", "docs"),
+        ("/both/", "both html"),
+        ("/my%20docs/", "my docs"),
+        ("/docs/./", "docs"),
+    ]
+    .iter()
+    {
+        let response = get_with(root.config(), target);
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(
+            response.contains("\r\nContent-Type: text/html; charset=utf-8\r\n"),
+            "{}",
+            response
+        );
+        assert!(
+            response.ends_with(&format!("\r\n\r\n{}", body)),
+            "{}",
+            response
+        );
+    }
+}
+
+#[test]
+fn index_files_are_configurable() {
+    let root = Root::with_files(&FILES);
+    let mut config = root.config();
+    config.index_files_mut(vec!["index.htm".to_string(), "index.html".to_string()]);
+
+    let response = get_with(config, "/both/");
+
+    assert!(response.ends_with("\r\n\r\nboth htm"), "{}", response);
+}
+
+#[test]
+fn directories_without_index_file_are_not_found() {
+    let root = Root::with_files(&FILES);
+    let response = get_with(root.config(), "/empty/");
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+
+    let mut config = root.config();
+    config.index_files_mut(Vec::new());
+    let response = get_with(config, "/docs/");
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn directories_without_trailing_slash_are_redirected() {
+    let root = Root::with_files(&FILES);
+    for (target, location) in [
+        ("/docs", "/docs/"),
+        ("/docs?page=2", "/docs/?page=2"),
+        ("/both/../docs", "/docs/"),
+        ("/my%20docs", "/my%20docs/"),
+        ("/empty", "/empty/"),
+    ]
+    .iter()
+    {
+        let response = get_with(root.config(), target);
+
+        assert!(
+            response.starts_with("HTTP/1.1 301 Moved Permanently\r\n"),
+            "{}",
+            response
+        );
+        assert!(
+            response.contains(&format!("\r\nLocation: {}\r\n", location)),
+            "{}: {}",
+            target,
+            response
+        );
+    }
+}
+
+#[test]
+fn redirect_queries_are_percent_encoded() {
+    let root = Root::with_files(&FILES);
+
+    // Control characters in the query must not reach the Location header raw
+    let responses = root.exchange(
+        b"GET /docs?\0 HTTP/1.1\r\n\r\n\
+          GET /docs?a=\"<b>\"&c=%2F%zz HTTP/1.1\r\nConnection: close\r\n\r\n",
+    );
+    let (first, second) = responses.split_at(responses.rfind("HTTP/1.1 ").unwrap());
+
+    assert!(
+        first.starts_with("HTTP/1.1 301 Moved Permanently\r\n"),
+        "{}",
+        first
+    );
+    assert_eq!(common::header(first, "Location"), Some("/docs/?%00"));
+    assert_eq!(
+        common::header(second, "Location"),
+        Some("/docs/?a=%22%3Cb%3E%22&c=%2F%25zz")
+    );
+}
+
+#[test]
+fn files_with_trailing_slash_are_not_found() {
+    let root = Root::with_files(&FILES);
+    let response = get_with(root.config(), "/index.html/");
+
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+}
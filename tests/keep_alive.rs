@@ -0,0 +1,157 @@
+//! Persistent connections end on the client's request, when idle or after max_requests
+
+mod common;
+
+use common::{header, status, Root};
+use linda::config::{Config, DEFAULT_KEEP_ALIVE_TIMEOUT, DEFAULT_MAX_REQUESTS};
+use linda::{handle_connection, handle_connection_with};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use std::{env, thread};
+
+const GET: &str = "GET /notes.txt HTTP/1.1\r\n\r\n";
+
+/// Send `requests` on a connection served with a short keep-alive timeout,
+/// leave it open and return what was received until the server closed it
+fn idle_exchange(root: &Root, requests: &[u8]) -> (String, Duration) {
+    let mut config = root.config();
+    config.keep_alive_timeout_mut(Duration::from_millis(200));
+    let (address, server) = common::serve(config);
+
+    let mut client = TcpStream::connect(address).unwrap();
+    let start = Instant::now();
+    client.write_all(requests).unwrap();
+
+    let mut responses = String::new();
+    client.read_to_string(&mut responses).unwrap();
+    drop(client);
+    server.join().unwrap();
+
+    (responses, start.elapsed())
+}
+
+#[test]
+fn idle_connections_are_closed_after_the_timeout() {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+
+    let (responses, elapsed) = idle_exchange(&root, GET.as_bytes());
+
+    assert_eq!(responses.matches("HTTP/1.1 200 OK\r\n").count(), 1);
+    assert_eq!(header(&responses, "Connection"), Some("keep-alive"));
+    assert!(responses.ends_with("\r\n\r\nnotes"), "{}", responses);
+    assert!(elapsed >= Duration::from_millis(200), "{:?}", elapsed);
+}
+
+#[test]
+fn incomplete_heads_time_out() {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+
+    let (response, _) = idle_exchange(&root, b"GET /notes.txt HTTP/1.1\r\nHost: loc");
+
+    assert!(
+        response.starts_with("HTTP/1.1 408 Request Timeout\r\n"),
+        "{}",
+        response
+    );
+    assert_eq!(header(&response, "Connection"), Some("close"));
+}
+
+#[test]
+fn connections_close_after_max_requests() {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+    let mut config = root.config();
+    config.max_requests_mut(2);
+
+    let responses = common::exchange_with(config, GET.repeat(3).as_bytes());
+
+    assert_eq!(responses.matches("HTTP/1.1 200 OK\r\n").count(), 2);
+    let (first, last) = responses.split_at(responses.rfind("HTTP/1.1 ").unwrap());
+    assert_eq!(header(first, "Connection"), Some("keep-alive"));
+    assert_eq!(header(last, "Connection"), Some("close"));
+}
+
+#[test]
+fn connection_close_ends_the_connection() {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+
+    let responses = root.exchange(
+        format!(
+            "GET /notes.txt HTTP/1.1\r\nConnection: close\r\n\r\n{}",
+            GET
+        )
+        .as_bytes(),
+    );
+
+    assert_eq!(status(&responses), "200");
+    assert_eq!(responses.matches("HTTP/1.1 ").count(), 1, "{}", responses);
+    assert_eq!(header(&responses, "Connection"), Some("close"));
+}
+
+#[test]
+fn zero_limits_from_the_environment_are_ignored() {
+    // The only test reading these variables
+    env::set_var("LINDA_KEEP_ALIVE_TIMEOUT", "0");
+    env::set_var("LINDA_MAX_REQUESTS", "0");
+
+    let config = Config::from_env();
+
+    env::remove_var("LINDA_KEEP_ALIVE_TIMEOUT");
+    env::remove_var("LINDA_MAX_REQUESTS");
+
+    assert_eq!(config.keep_alive_timeout(), DEFAULT_KEEP_ALIVE_TIMEOUT);
+    assert_eq!(config.max_requests(), DEFAULT_MAX_REQUESTS);
+}
+
+#[test]
+fn idle_connections_give_up_busy_workers() {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+    let config = root.config();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection_with(stream, &config, &|| true).unwrap();
+    });
+
+    let mut client = TcpStream::connect(address).unwrap();
+    let start = Instant::now();
+    client.write_all(GET.as_bytes()).unwrap();
+
+    let mut responses = String::new();
+    client.read_to_string(&mut responses).unwrap();
+    drop(client);
+    server.join().unwrap();
+
+    // The first request is served, the connection is not kept open idle
+    assert_eq!(status(&responses), "200", "{}", responses);
+    assert!(responses.ends_with("\r\n\r\nnotes"), "{}", responses);
+    assert!(start.elapsed() < DEFAULT_KEEP_ALIVE_TIMEOUT / 2);
+}
+
+#[test]
+fn clients_not_reading_are_disconnected() {
+    let root = Root::with_files(&[("large.bin", vec![0; 1024 * 1024])]);
+    let mut config = root.config();
+    config.write_timeout_mut(Duration::from_millis(200));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        sender
+            .send(handle_connection(stream, &config).is_err())
+            .unwrap();
+    });
+
+    // Far more than fits in the socket buffers, and never read
+    let mut client = TcpStream::connect(address).unwrap();
+    client
+        .write_all("GET /large.bin HTTP/1.1\r\n\r\n".repeat(64).as_bytes())
+        .unwrap();
+
+    let failed = receiver.recv_timeout(Duration::from_secs(10));
+    assert_eq!(failed, Ok(true));
+}
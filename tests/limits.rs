@@ -0,0 +1,137 @@
+//! Request heads are accumulated over several reads, within the configured limits
+
+mod common;
+
+use common::{header, status, Root};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn heads_split_across_reads_are_reassembled() {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+    let (address, server) = common::serve(root.config());
+
+    let mut client = TcpStream::connect(address).unwrap();
+    client.set_nodelay(true).unwrap();
+    for fragment in [
+        "GE",
+        "T /notes",
+        ".txt HTTP/1.1\r",
+        "\nHo",
+        "st: localhost\r\nConnection",
+        ": close\r\n\r",
+        "\n",
+    ]
+    .iter()
+    {
+        client.write_all(fragment.as_bytes()).unwrap();
+        // Give the server time to read every fragment on its own
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    drop(client);
+    server.join().unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with("\r\n\r\nnotes"), "{}", response);
+}
+
+#[test]
+fn long_request_lines_are_uri_too_long() {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+    let mut config = root.config();
+    config.max_request_line_mut(64);
+
+    let target = format!("/{}", "a".repeat(64));
+    let response = common::exchange_with(config, &common::request("GET", &target, ""));
+
+    assert!(
+        response.starts_with("HTTP/1.1 414 URI Too Long\r\n"),
+        "{}",
+        response
+    );
+    assert_eq!(header(&response, "Connection"), Some("close"));
+}
+
+#[test]
+fn request_lines_within_the_limit_are_served() {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+    let mut config = root.config();
+    // "GET /notes.txt HTTP/1.1\r\n"
+    config.max_request_line_mut(25);
+
+    let response = common::exchange_with(config, &common::request("GET", "/notes.txt", ""));
+
+    assert_eq!(status(&response), "200", "{}", response);
+}
+
+#[test]
+fn large_heads_are_header_fields_too_large() {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+    let mut config = root.config();
+    config.max_head_size_mut(256);
+
+    let headers = format!("X-Padding: {}\r\n", "a".repeat(256));
+    let response = common::exchange_with(config, &common::request("GET", "/notes.txt", &headers));
+
+    assert!(
+        response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"),
+        "{}",
+        response
+    );
+    assert_eq!(header(&response, "Connection"), Some("close"));
+}
+
+/// Send `request` from another thread while reading the response, which has to
+/// arrive in full although the server stops reading the request early
+fn exchange_oversized(request: Vec<u8>) -> String {
+    let root = Root::with_files(&[("notes.txt", "notes")]);
+    let (address, server) = common::serve(root.config());
+
+    let mut client = TcpStream::connect(address).unwrap();
+    let mut writer = client.try_clone().unwrap();
+    // Fails once the server stops reading, which may be before everything was sent
+    let sender = thread::spawn(move || {
+        let _ = writer.write_all(&request);
+    });
+
+    let mut response = Vec::new();
+    let read = client.read_to_end(&mut response);
+    drop(client);
+    sender.join().unwrap();
+    server.join().unwrap();
+
+    read.expect("connection reset before the response was read");
+    String::from_utf8(response).unwrap()
+}
+
+#[test]
+fn oversized_heads_are_answered_before_closing() {
+    let headers = format!("X-Padding: {}\r\n", "a".repeat(200 * 1024));
+    let response = exchange_oversized(common::request("GET", "/notes.txt", &headers));
+
+    assert!(
+        response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn oversized_bodies_are_answered_before_closing() {
+    let body = vec![b'x'; 4 * 1024 * 1024];
+    let mut request = common::request("POST", "/", &format!("Content-Length: {}\r\n", body.len()));
+    request.extend_from_slice(&body);
+
+    let response = exchange_oversized(request);
+
+    assert!(
+        response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"),
+        "{}",
+        response
+    );
+}
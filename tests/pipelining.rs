@@ -0,0 +1,166 @@
+//! Pipelined requests sent in a single write must be answered in order
+
+mod common;
+
+use common::Root;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Send `requests` in one write to a document root with a couple of files
+///
+/// The values of ETag and Last-Modified headers are replaced by `<etag>` and `<date>`
+fn exchange(requests: &[u8]) -> String {
+    let root = Root::new();
+    root.file("index.html", "<h1>Linda</h1>\n")
+        .file("notes.txt", "pipelined\n");
+
+    root.exchange(requests)
+        .split("\r\n")
+        .map(|line| {
+            if line.starts_with("ETag: ") {
+                "ETag: <etag>"
+            } else if line.starts_with("Last-Modified: ") {
+                "Last-Modified: <date>"
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+#[test]
+fn get_and_head_responses_are_ordered() {
+    let responses = exchange(
+        b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n\
+          HEAD /notes.txt HTTP/1.1\r\nHost: localhost\r\n\r\n\
+          GET /missing.html HTTP/1.1\r\nHost: localhost\r\n\r\n\
+          GET /notes.txt HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+
+    assert_eq!(
+        responses,
+        "HTTP/1.1 200 OK\r\n\
+         Date: <date>\r\n\
+         Content-Type: text/html; charset=utf-8\r\n\
+         Accept-Ranges: bytes\r\n\
+         ETag: <etag>\r\n\
+         Last-Modified: <date>\r\n\
+         Server: Linda\r\n\
+         Connection: keep-alive\r\n\
+         Content-Length: 15\r\n\
+         \r\n\
+         <h1>Linda</h1>\n\
+         HTTP/1.1 200 OK\r\n\
+         Date: <date>\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Accept-Ranges: bytes\r\n\
+         ETag: <etag>\r\n\
+         Last-Modified: <date>\r\n\
+         Content-Length: 10\r\n\
+         Server: Linda\r\n\
+         Connection: keep-alive\r\n\
+         \r\n\
+         HTTP/1.1 404 Not Found\r\n\
+         Date: <date>\r\n\
+         Content-Type: text/html; charset=utf-8\r\n\
+         Server: Linda\r\n\
+         Connection: keep-alive\r\n\
+         Content-Length: 0\r\n\
+         \r\n\
+         HTTP/1.1 200 OK\r\n\
+         Date: <date>\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Accept-Ranges: bytes\r\n\
+         ETag: <etag>\r\n\
+         Last-Modified: <date>\r\n\
+         Server: Linda\r\n\
+         Connection: close\r\n\
+         Content-Length: 10\r\n\
+         \r\n\
+         pipelined\n"
+    );
+}
+
+#[test]
+fn request_bodies_do_not_leak_into_the_next_request() {
+    let responses = exchange(
+        b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 15\r\n\r\n\
+          GET /notes.txt \
+          POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+          5\r\nHEAD \r\n0\r\n\r\n\
+          HEAD / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+
+    assert_eq!(
+        responses,
+        "HTTP/1.1 501 Not Implemented\r\n\
+         Date: <date>\r\n\
+         Server: Linda\r\n\
+         Connection: keep-alive\r\n\
+         Content-Length: 0\r\n\
+         \r\n\
+         HTTP/1.1 501 Not Implemented\r\n\
+         Date: <date>\r\n\
+         Server: Linda\r\n\
+         Connection: keep-alive\r\n\
+         Content-Length: 0\r\n\
+         \r\n\
+         HTTP/1.1 200 OK\r\n\
+         Date: <date>\r\n\
+         Content-Type: text/html; charset=utf-8\r\n\
+         Accept-Ranges: bytes\r\n\
+         ETag: <etag>\r\n\
+         Last-Modified: <date>\r\n\
+         Content-Length: 15\r\n\
+         Server: Linda\r\n\
+         Connection: close\r\n\
+         \r\n"
+    );
+}
+
+#[test]
+fn responses_are_sent_before_waiting_for_a_body() {
+    let root = Root::new();
+    root.file("notes.txt", "pipelined\n");
+    // Files sent with sendfile(2) flush the writer anyway
+    let mut config = root.config();
+    config.zero_copy_mut(false);
+    let (address, server) = common::serve(config);
+
+    let mut client = TcpStream::connect(address).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    // The head of the second request arrives with the first request, its body does not
+    client
+        .write_all(
+            b"GET /notes.txt HTTP/1.1\r\n\r\n\
+              POST / HTTP/1.1\r\nContent-Length: 5\r\nConnection: close\r\n\r\n",
+        )
+        .unwrap();
+
+    let mut first = Vec::new();
+    while !first.ends_with(b"\r\n\r\npipelined\n") {
+        let mut buffer = [0; 1024];
+        let read = client
+            .read(&mut buffer)
+            .expect("first response held back while reading the second body");
+        assert!(read > 0, "{}", String::from_utf8_lossy(&first));
+        first.extend_from_slice(&buffer[..read]);
+    }
+
+    client.write_all(b"hello").unwrap();
+    let mut second = String::new();
+    client.read_to_string(&mut second).unwrap();
+    drop(client);
+    server.join().unwrap();
+
+    assert!(first.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    assert!(
+        second.starts_with("HTTP/1.1 501 Not Implemented\r\n"),
+        "{}",
+        second
+    );
+}
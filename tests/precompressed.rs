@@ -0,0 +1,150 @@
+//! Precompressed siblings of files are served to clients accepting their encoding
+
+mod common;
+
+use common::{header, request_with, Root};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+const CSS: &str = "body { color: black; }\n";
+
+/// Not actually Brotli, the server never decodes it
+const BROTLI: &[u8] = b"brotli bytes";
+
+/// app.css with its .gz and .br siblings, and a .gz file without the plain file
+fn files() -> [(&'static str, Vec<u8>); 4] {
+    [
+        ("app.css", CSS.into()),
+        ("app.css.gz", gzip()),
+        ("app.css.br", BROTLI.into()),
+        ("orphan.js.gz", gzip()),
+    ]
+}
+
+fn gzip() -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(CSS.as_bytes()).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn get(root: &Root, accept_encoding: &str) -> (String, Vec<u8>) {
+    let headers = format!("Accept-Encoding: {}\r\n", accept_encoding);
+    request_with(root.config(), "GET", "/app.css", &headers)
+}
+
+#[test]
+fn brotli_is_preferred() {
+    let root = Root::with_files(&files());
+    let (head, body) = get(&root, "gzip, deflate, br, zstd");
+
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+    assert_eq!(
+        header(&head, "Content-Type"),
+        Some("text/css; charset=utf-8")
+    );
+    assert_eq!(header(&head, "Content-Encoding"), Some("br"));
+    assert_eq!(header(&head, "Vary"), Some("Accept-Encoding"));
+    assert_eq!(body, BROTLI);
+}
+
+#[test]
+fn negotiation_picks_an_available_sibling() {
+    let root = Root::with_files(&files());
+    for accept_encoding in ["gzip", "x-gzip, zstd", "br;q=0.5, gzip"].iter() {
+        let (head, body) = get(&root, accept_encoding);
+
+        assert_eq!(
+            header(&head, "Content-Encoding"),
+            Some("gzip"),
+            "{}",
+            accept_encoding
+        );
+        assert_eq!(
+            header(&head, "Content-Type"),
+            Some("text/css; charset=utf-8")
+        );
+        assert_eq!(body, gzip(), "{}", accept_encoding);
+    }
+}
+
+#[test]
+fn plain_file_is_the_fallback() {
+    let root = Root::with_files(&files());
+    for headers in [
+        "",
+        "Accept-Encoding: deflate\r\n",
+        "Accept-Encoding: identity\r\n",
+    ]
+    .iter()
+    {
+        let (head, body) = request_with(root.config(), "GET", "/app.css", headers);
+
+        assert_eq!(header(&head, "Content-Encoding"), None, "{}", headers);
+        assert_eq!(header(&head, "Vary"), Some("Accept-Encoding"));
+        assert_eq!(body, CSS.as_bytes(), "{}", headers);
+    }
+}
+
+#[test]
+fn siblings_have_their_own_validators() {
+    let root = Root::with_files(&files());
+    let (plain, _) = get(&root, "identity");
+    let (encoded, _) = get(&root, "gzip");
+
+    assert_ne!(header(&plain, "ETag"), header(&encoded, "ETag"));
+
+    let (head, _) = request_with(
+        root.config(),
+        "GET",
+        "/app.css",
+        &format!(
+            "Accept-Encoding: gzip\r\nIf-None-Match: {}\r\n",
+            header(&encoded, "ETag").unwrap()
+        ),
+    );
+    assert!(
+        head.starts_with("HTTP/1.1 304 Not Modified\r\n"),
+        "{}",
+        head
+    );
+    assert_eq!(header(&head, "Vary"), Some("Accept-Encoding"));
+}
+
+#[test]
+fn head_requests_describe_the_sibling() {
+    let root = Root::with_files(&files());
+    let (head, body) = request_with(root.config(), "HEAD", "/app.css", "Accept-Encoding: br\r\n");
+
+    assert_eq!(header(&head, "Content-Encoding"), Some("br"));
+    assert_eq!(
+        header(&head, "Content-Length"),
+        Some(BROTLI.len().to_string().as_str())
+    );
+    assert!(body.is_empty());
+}
+
+#[test]
+fn siblings_without_the_plain_file_are_not_served() {
+    let root = Root::with_files(&files());
+    let (head, _) = request_with(
+        root.config(),
+        "GET",
+        "/orphan.js",
+        "Accept-Encoding: gzip\r\n",
+    );
+
+    assert!(head.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", head);
+}
+
+#[test]
+fn siblings_can_be_ignored() {
+    let root = Root::with_files(&files());
+    let mut config = root.config();
+    config.precompressed_mut(false);
+    let (head, body) = request_with(config, "GET", "/app.css", "Accept-Encoding: br, gzip\r\n");
+
+    assert_eq!(header(&head, "Content-Encoding"), None);
+    assert_eq!(header(&head, "Vary"), None);
+    assert_eq!(body, CSS.as_bytes());
+}
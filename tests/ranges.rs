@@ -0,0 +1,211 @@
+//! Range requests are answered with 206 Partial Content
+
+mod common;
+
+use common::{body, Root};
+use linda::date::http_date;
+use std::fs;
+
+const DIGITS: &str = "0123456789abcdefghij";
+
+/// Send a request for /digits.txt with extra header lines and return the response
+fn request(root: &Root, method: &str, headers: &str) -> String {
+    root.exchange(&common::request(method, "/digits.txt", headers))
+}
+
+#[test]
+fn full_responses_advertise_range_support() {
+    let root = Root::with_files(&[("digits.txt", DIGITS)]);
+    let response = request(&root, "GET", "");
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(
+        response.contains("\r\nAccept-Ranges: bytes\r\n"),
+        "{}",
+        response
+    );
+    assert_eq!(body(&response), DIGITS);
+}
+
+#[test]
+fn single_ranges_are_partial_content() {
+    let root = Root::with_files(&[("digits.txt", DIGITS)]);
+    for (range, content_range, part) in [
+        ("bytes=0-4", "bytes 0-4/20", "01234"),
+        ("bytes=15-", "bytes 15-19/20", "fghij"),
+        ("bytes=-3", "bytes 17-19/20", "hij"),
+        ("bytes=-50", "bytes 0-19/20", DIGITS),
+        ("bytes=18-100", "bytes 18-19/20", "ij"),
+        ("bytes=2-4, 3-6", "bytes 2-6/20", "23456"),
+        ("bytes=30-40, 5-5", "bytes 5-5/20", "5"),
+    ]
+    .iter()
+    {
+        let response = request(&root, "GET", &format!("Range: {}\r\n", range));
+
+        assert!(
+            response.starts_with("HTTP/1.1 206 Partial Content\r\n"),
+            "{}: {}",
+            range,
+            response
+        );
+        assert!(
+            response.contains(&format!("\r\nContent-Range: {}\r\n", content_range)),
+            "{}: {}",
+            range,
+            response
+        );
+        assert!(
+            response.contains(&format!("\r\nContent-Length: {}\r\n", part.len())),
+            "{}: {}",
+            range,
+            response
+        );
+        assert!(
+            response.contains("\r\nContent-Type: text/plain; charset=utf-8\r\n"),
+            "{}",
+            response
+        );
+        assert_eq!(body(&response), *part, "{}", range);
+    }
+}
+
+#[test]
+fn multiple_ranges_are_multipart_byteranges() {
+    let root = Root::with_files(&[("digits.txt", DIGITS)]);
+    let response = request(&root, "GET", "Range: bytes=0-1,-2\r\n");
+
+    assert!(
+        response.starts_with("HTTP/1.1 206 Partial Content\r\n"),
+        "{}",
+        response
+    );
+    let head = &response[..response.find("\r\n\r\n").unwrap()];
+    assert!(!head.contains("\r\nContent-Range:"), "{}", response);
+
+    let marker = "\r\nContent-Type: multipart/byteranges; boundary=";
+    let start = response.find(marker).unwrap() + marker.len();
+    let boundary = &response[start..start + response[start..].find("\r\n").unwrap()];
+
+    assert_eq!(
+        body(&response),
+        format!(
+            "--{0}\r\n\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             Content-Range: bytes 0-1/20\r\n\
+             \r\n\
+             01\r\n\
+             --{0}\r\n\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             Content-Range: bytes 18-19/20\r\n\
+             \r\n\
+             ij\r\n\
+             --{0}--\r\n",
+            boundary
+        )
+    );
+}
+
+#[test]
+fn unsatisfiable_ranges_are_rejected() {
+    let root = Root::with_files(&[("digits.txt", DIGITS)]);
+    let response = request(&root, "GET", "Range: bytes=20-,30-40\r\n");
+
+    assert!(
+        response.starts_with("HTTP/1.1 416 Range Not Satisfiable\r\n"),
+        "{}",
+        response
+    );
+    assert!(
+        response.contains("\r\nContent-Range: bytes */20\r\n"),
+        "{}",
+        response
+    );
+    assert!(
+        response.contains("\r\nContent-Length: 0\r\n"),
+        "{}",
+        response
+    );
+    assert_eq!(body(&response), "");
+}
+
+#[test]
+fn invalid_ranges_are_ignored() {
+    let root = Root::with_files(&[("digits.txt", DIGITS)]);
+    for range in [
+        "bytes=5-1",
+        "bytes=a-b",
+        "items=0-4",
+        "bytes=",
+        "bytes=0-1;2-3",
+        "bytesé",
+        "byté=0-4",
+    ]
+    .iter()
+    {
+        let response = request(&root, "GET", &format!("Range: {}\r\n", range));
+
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK\r\n"),
+            "{}: {}",
+            range,
+            response
+        );
+        assert_eq!(body(&response), DIGITS, "{}", range);
+    }
+}
+
+#[test]
+fn head_requests_ignore_ranges() {
+    let root = Root::with_files(&[("digits.txt", DIGITS)]);
+    let response = request(&root, "HEAD", "Range: bytes=0-4\r\n");
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(
+        response.contains("\r\nContent-Length: 20\r\n"),
+        "{}",
+        response
+    );
+}
+
+#[test]
+fn if_range_only_honours_ranges_of_unchanged_files() {
+    let root = Root::with_files(&[("digits.txt", DIGITS)]);
+    let modified = fs::metadata(root.path().join("digits.txt"))
+        .unwrap()
+        .modified()
+        .unwrap();
+
+    let response = request(
+        &root,
+        "GET",
+        &format!("Range: bytes=0-4\r\nIf-Range: {}\r\n", http_date(modified)),
+    );
+    assert!(
+        response.starts_with("HTTP/1.1 206 Partial Content\r\n"),
+        "{}",
+        response
+    );
+
+    for if_range in [
+        "Sun, 06 Nov 1994 08:49:37 GMT",
+        "\"some-etag\"",
+        "W/\"weak\"",
+    ]
+    .iter()
+    {
+        let response = request(
+            &root,
+            "GET",
+            &format!("Range: bytes=0-4\r\nIf-Range: {}\r\n", if_range),
+        );
+
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK\r\n"),
+            "{}: {}",
+            if_range,
+            response
+        );
+        assert_eq!(body(&response), DIGITS, "{}", if_range);
+    }
+}
@@ -0,0 +1,165 @@
+//! File bodies are streamed in chunks rather than read into memory, and bodies
+//! already in memory are not copied into the write buffer when they do not fit
+
+mod common;
+
+use common::{header, split_head, Root};
+use linda::body::CHUNK_SIZE;
+use linda::response::Response;
+use std::io::{BufWriter, Read};
+use std::net::{TcpListener, TcpStream};
+
+/// Three chunks and a bit of bytes that differ from one chunk to the next
+fn large() -> Vec<u8> {
+    (0..3 * CHUNK_SIZE + 17).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn large_files_are_sent_whole() {
+    let root = Root::with_files(&[("large.bin", large())]);
+    let responses = root.exchange_bytes(b"GET /large.bin HTTP/1.1\r\nConnection: close\r\n\r\n");
+    let (head, body) = split_head(&responses);
+
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+    assert_eq!(
+        header(&head, "Content-Length"),
+        Some(large().len().to_string().as_str())
+    );
+    assert!(body == &large()[..]);
+}
+
+#[test]
+fn ranges_across_chunks_are_read_from_their_offset() {
+    let root = Root::with_files(&[("large.bin", large())]);
+    let (start, end) = (CHUNK_SIZE - 3, 2 * CHUNK_SIZE + 4);
+    let responses = root.exchange_bytes(
+        format!(
+            "GET /large.bin HTTP/1.1\r\nRange: bytes={}-{}\r\nConnection: close\r\n\r\n",
+            start, end
+        )
+        .as_bytes(),
+    );
+    let (head, body) = split_head(&responses);
+
+    assert!(
+        head.starts_with("HTTP/1.1 206 Partial Content\r\n"),
+        "{}",
+        head
+    );
+    assert!(body == &large()[start..=end]);
+}
+
+#[test]
+fn multipart_ranges_share_the_file() {
+    let root = Root::with_files(&[("large.bin", large())]);
+    let responses = root.exchange_bytes(
+        b"GET /large.bin HTTP/1.1\r\nRange: bytes=-2,0-1\r\nConnection: close\r\n\r\n",
+    );
+    let (head, body) = split_head(&responses);
+    let large = large();
+
+    assert!(
+        head.starts_with("HTTP/1.1 206 Partial Content\r\n"),
+        "{}",
+        head
+    );
+    let (part, rest) = split_head(body);
+    assert!(part.contains("Content-Range: bytes 0-1/"), "{}", part);
+    assert_eq!(rest[..2], large[..2]);
+    let (part, rest) = split_head(&rest[2..]);
+    assert!(
+        part.contains("Content-Range: bytes 196623-196624/"),
+        "{}",
+        part
+    );
+    assert_eq!(rest[..2], large[large.len() - 2..]);
+}
+
+#[test]
+fn pipelined_requests_stay_in_sync_after_large_bodies() {
+    let root = Root::with_files(&[("large.bin", large())]);
+    let responses = root.exchange_bytes(
+        b"GET /large.bin HTTP/1.1\r\n\r\n\
+         HEAD /large.bin HTTP/1.1\r\n\r\n\
+         GET /large.bin HTTP/1.1\r\nRange: bytes=0-9\r\nConnection: close\r\n\r\n",
+    );
+
+    let (head, rest) = split_head(&responses);
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+    assert!(rest[..large().len()] == large()[..]);
+
+    let (head, rest) = split_head(&rest[large().len()..]);
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+    assert_eq!(
+        header(&head, "Content-Length"),
+        Some(large().len().to_string().as_str())
+    );
+
+    let (head, rest) = split_head(rest);
+    assert!(
+        head.starts_with("HTTP/1.1 206 Partial Content\r\n"),
+        "{}",
+        head
+    );
+    assert!(rest == &large()[..10]);
+}
+
+#[test]
+fn buffered_copies_send_the_same_bytes() {
+    let root = Root::with_files(&[("large.bin", large())]);
+    let requests = "GET /large.bin HTTP/1.1\r\n\r\n\
+                    GET /large.bin HTTP/1.1\r\nRange: bytes=70000-70009\r\nConnection: close\r\n\r\n";
+    let mut config = root.config();
+    config.zero_copy_mut(false);
+
+    let buffered = common::exchange_bytes(config, requests.as_bytes());
+    let zero_copy = root.exchange_bytes(requests.as_bytes());
+
+    let (head, rest) = split_head(&buffered);
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+    assert!(rest[..large().len()] == large()[..]);
+    let (head, rest) = split_head(&rest[large().len()..]);
+    assert!(
+        head.starts_with("HTTP/1.1 206 Partial Content\r\n"),
+        "{}",
+        head
+    );
+    assert!(rest == &large()[70000..70010]);
+
+    // Only the Date headers may differ
+    assert_eq!(buffered.len(), zero_copy.len());
+}
+
+#[test]
+fn in_memory_bodies_that_do_not_fit_skip_the_buffer() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    let mut writer = BufWriter::with_capacity(1024, &stream);
+    let mut head = Vec::new();
+    let (first, second) = ("a".repeat(400), "b".repeat(800));
+
+    // Responses that fit are gathered for the responses to pipelined requests
+    Response::text(first.as_str())
+        .send_to(&mut writer, &mut head)
+        .unwrap();
+    assert!(writer.buffer().len() > first.len());
+
+    // Fits in the buffer, but not next to the first response. Copying it into
+    // the buffer after flushing would leave it there.
+    Response::text(second.as_str())
+        .send_to(&mut writer, &mut head)
+        .unwrap();
+    assert!(writer.buffer().is_empty());
+
+    drop(writer);
+    drop(stream);
+    let mut responses = Vec::new();
+    client.read_to_end(&mut responses).unwrap();
+
+    let (_, rest) = split_head(&responses);
+    assert!(rest[..first.len()] == *first.as_bytes());
+    let (head, rest) = split_head(&rest[first.len()..]);
+    assert_eq!(header(&head, "Content-Length"), Some("800"));
+    assert!(rest == second.as_bytes());
+}
@@ -0,0 +1,200 @@
+//! Known path traversal payloads must never serve files outside of the document root
+
+mod common;
+
+use common::Root;
+use linda::uri::normalize_path;
+
+/// Marker contained in every file outside of the document root
+const SECRET: &str = "top secret";
+
+/// Request every target in turn and return the responses
+///
+/// The document root lies next to secret files, with symbolic links pointing out of it:
+///
+/// base/
+/// ├── secret.txt
+/// ├── outside/secret.txt
+/// └── root/
+///     ├── index.html
+///     ├── docs/page.html
+///     ├── leak.txt     -> ../secret.txt
+///     └── link         -> ../outside
+fn get_all(targets: &[&str]) -> Vec<String> {
+    let root = Root::with_files(&[("index.html", "index"), ("docs/page.html", "page")]);
+    root.outside("secret.txt", SECRET)
+        .outside("outside/secret.txt", SECRET);
+    #[cfg(unix)]
+    root.symlink("leak.txt", "secret.txt")
+        .symlink("link", "outside");
+
+    targets
+        .iter()
+        .map(|target| root.exchange(&common::request("GET", target, "")))
+        .collect()
+}
+
+const PAYLOADS: [&str; 18] = [
+    "/../secret.txt",
+    "/../../../../../../etc/passwd",
+    "/docs/../../secret.txt",
+    "/docs/./../../secret.txt",
+    "/./../secret.txt",
+    "/..",
+    "/..%2fsecret.txt",
+    "/..%2Fsecret.txt",
+    "/docs%2f..%2f..%2fsecret.txt",
+    "/..%5csecret.txt",
+    "/..%5Csecret.txt",
+    "/..\\secret.txt",
+    "/docs\\..\\..\\secret.txt",
+    "/index.html%00.png",
+    "/secret.txt%00",
+    "..",
+    "../secret.txt",
+    "secret.txt",
+];
+
+#[test]
+fn payloads_are_rejected_by_normalization() {
+    // Percent-encoded payloads are rejected before decoding, see decode_path
+    for payload in PAYLOADS.iter().filter(|payload| !payload.contains('%')) {
+        assert!(normalize_path(payload).is_err(), "{}", payload);
+    }
+}
+
+#[test]
+fn safe_paths_are_normalized() {
+    let paths = [
+        ("/", "/"),
+        ("/index.html", "/index.html"),
+        ("//index.html", "/index.html"),
+        ("/docs/", "/docs/"),
+        ("/docs/.", "/docs/"),
+        ("/docs/page.html/..", "/docs/"),
+        ("/docs/../index.html", "/index.html"),
+        ("/docs/./page.html", "/docs/page.html"),
+        ("/docs//page.html", "/docs/page.html"),
+        ("/....//....//etc/passwd", "/..../..../etc/passwd"),
+        ("/%252e%252e/secret.txt", "/%252e%252e/secret.txt"),
+    ];
+
+    for (path, normalized) in paths.iter() {
+        assert_eq!(normalize_path(path).unwrap(), *normalized, "{}", path);
+    }
+}
+
+#[test]
+fn payloads_are_answered_with_bad_request() {
+    for (payload, response) in PAYLOADS.iter().zip(get_all(&PAYLOADS)) {
+        assert!(
+            response.starts_with("HTTP/1.1 400 "),
+            "{}: {}",
+            payload,
+            response
+        );
+        assert!(!response.contains(SECRET), "{}", payload);
+    }
+}
+
+#[test]
+fn lookalike_payloads_stay_inside_the_root() {
+    let payloads = [
+        "//etc/passwd",
+        "/....//....//secret.txt",
+        "/%252e%252e/secret.txt",
+        "/..;/secret.txt",
+        "/.../secret.txt",
+    ];
+
+    for (payload, response) in payloads.iter().zip(get_all(&payloads)) {
+        assert!(
+            response.starts_with("HTTP/1.1 404 "),
+            "{}: {}",
+            payload,
+            response
+        );
+        assert!(!response.contains(SECRET), "{}", payload);
+    }
+}
+
+#[test]
+fn encoded_payloads_are_decoded_before_normalization() {
+    let payloads = [
+        "/%2e%2e/secret.txt",
+        "/%2E%2E/%2e%2e/etc/passwd",
+        "/docs/%2e%2e/%2e./secret.txt",
+        "/docs/%2e%2e/%2e%2e/secret.txt?file=index.html",
+        "/%ZZ/secret.txt",
+        "/secret.txt%",
+        "/%ff%fe/secret.txt",
+    ];
+
+    for (payload, response) in payloads.iter().zip(get_all(&payloads)) {
+        assert!(
+            response.starts_with("HTTP/1.1 400 "),
+            "{}: {}",
+            payload,
+            response
+        );
+        assert!(!response.contains(SECRET), "{}", payload);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn symbolic_links_out_of_the_root_are_forbidden() {
+    let payloads = ["/leak.txt", "/link/secret.txt"];
+    for (payload, response) in payloads.iter().zip(get_all(&payloads)) {
+        assert!(
+            response.starts_with("HTTP/1.1 403 "),
+            "{}: {}",
+            payload,
+            response
+        );
+        assert!(!response.contains(SECRET), "{}", payload);
+    }
+}
+
+#[test]
+fn files_inside_the_root_are_served() {
+    let payloads = [
+        "/docs/page.html",
+        "/docs/../docs/./page.html",
+        "/docs/%70age.html",
+        "/docs/%2e/page.html",
+        "/docs/page.html?download=1&file=../secret.txt",
+        "/docs/page.html#../../secret.txt",
+    ];
+
+    for (payload, response) in payloads.iter().zip(get_all(&payloads)) {
+        assert!(
+            response.starts_with("HTTP/1.1 200 "),
+            "{}: {}",
+            payload,
+            response
+        );
+        assert!(response.ends_with("\r\n\r\npage"), "{}", payload);
+    }
+}
+
+#[test]
+fn encoded_separators_in_file_names_are_served() {
+    let root = Root::with_files(&[("a%2fb.txt", "slash"), ("a%5cb.txt", "backslash")]);
+
+    let targets = [("/a%252fb.txt", "slash"), ("/a%255cb.txt", "backslash")];
+    for (target, body) in targets.iter() {
+        let response = root.exchange(&common::request("GET", target, ""));
+        assert!(
+            response.starts_with("HTTP/1.1 200 "),
+            "{}: {}",
+            target,
+            response
+        );
+        assert!(
+            response.ends_with(&format!("\r\n\r\n{}", body)),
+            "{}",
+            target
+        );
+    }
+}